@@ -0,0 +1,79 @@
+//! Throughput of SnowFlake generator, against the previous one that updated timestamp and sequence separately.
+//!
+//! ```sh
+//! cargo bench --bench snowflake
+//! ```
+
+use ruva::NumericalUniqueIdGenerator;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicI16, AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const IDS_PER_THREAD: usize = 1_000_000;
+
+// * Previous implementation, kept as baseline. It can hand out the same id to concurrent callers.
+struct LegacyGenerator {
+	timestamp: AtomicI64,
+	sequence_num: AtomicI16,
+}
+
+impl LegacyGenerator {
+	fn now() -> i64 {
+		SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64
+	}
+
+	fn generate(&self) -> i64 {
+		self.sequence_num.store((self.sequence_num.load(Ordering::Relaxed) + 1) % 4096, Ordering::Relaxed);
+		let mut now_millis = Self::now();
+		if self.timestamp.load(Ordering::Relaxed) == now_millis {
+			if self.sequence_num.load(Ordering::Relaxed) == 0 {
+				while now_millis <= self.timestamp.load(Ordering::Relaxed) {
+					now_millis = Self::now();
+				}
+				self.timestamp.store(now_millis, Ordering::Relaxed);
+			}
+		} else {
+			self.timestamp.store(now_millis, Ordering::Relaxed);
+			self.sequence_num.store(0, Ordering::Relaxed);
+		}
+		self.timestamp.load(Ordering::Relaxed) << 22 | (1 << 17) | (2 << 12) | (self.sequence_num.load(Ordering::Relaxed) as i64)
+	}
+}
+
+/// Generate ids on the given number of threads, returning ids per second and the number of duplicates.
+fn run(threads: usize, generate: impl Fn() -> i64 + Send + Sync + 'static) -> (f64, usize) {
+	let generate = Arc::new(generate);
+	let started = Instant::now();
+	let handles = (0..threads)
+		.map(|_| {
+			let generate = Arc::clone(&generate);
+			std::thread::spawn(move || (0..IDS_PER_THREAD).map(|_| black_box(generate())).collect::<Vec<_>>())
+		})
+		.collect::<Vec<_>>();
+	let mut ids = handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+	let elapsed = started.elapsed();
+
+	let generated = ids.len();
+	ids.sort_unstable();
+	ids.dedup();
+	(generated as f64 / elapsed.as_secs_f64(), generated - ids.len())
+}
+
+fn main() {
+	for threads in [1, 4, 8] {
+		let legacy = LegacyGenerator {
+			timestamp: AtomicI64::new(0),
+			sequence_num: AtomicI16::new(0),
+		};
+		let (legacy_rate, legacy_duplicates) = run(threads, move || legacy.generate());
+
+		let current = NumericalUniqueIdGenerator::new(1, 2);
+		let (rate, duplicates) = run(threads, move || current.generate());
+
+		println!(
+			"{threads} thread(s): legacy {:>12.0} ids/s ({legacy_duplicates} duplicates), current {:>12.0} ids/s ({duplicates} duplicates)",
+			legacy_rate, rate
+		);
+	}
+}
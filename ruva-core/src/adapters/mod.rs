@@ -1,2 +1,4 @@
 #[cfg(feature = "sqlx-postgres")]
 pub mod sqlx;
+#[cfg(feature = "webhook")]
+pub mod webhook;
@@ -1,9 +1,10 @@
-use crate::prelude::BaseError;
+use crate::prelude::{BaseError, Id};
 use crate::snowflake::SnowFlake;
 
 use sqlx::error::BoxDynError;
 use sqlx::postgres::{PgHasArrayType, PgTypeInfo, PgValueRef};
-use sqlx::{Encode, Postgres, Type};
+use sqlx::{Encode, Postgres, Type, TypeInfo, ValueRef};
+use uuid::Uuid;
 
 impl Encode<'_, Postgres> for SnowFlake {
 	fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, BoxDynError> {
@@ -32,6 +33,10 @@ impl sqlx::Type<Postgres> for SnowFlake {
 impl From<sqlx::Error> for BaseError {
 	fn from(value: sqlx::Error) -> Self {
 		tracing::error!("{:?}", value);
+		// * serialization_failure(40001) and deadlock_detected(40P01)
+		if value.as_database_error().and_then(|err| err.code()).is_some_and(|code| code == "40001" || code == "40P01") {
+			return Self::TransactionConflict(value.to_string());
+		}
 		Self::DatabaseError(value.to_string())
 	}
 }
@@ -41,3 +46,88 @@ impl PgHasArrayType for SnowFlake {
 		<i64 as PgHasArrayType>::array_type_info()
 	}
 }
+
+// * Id is bound as the type of its variant, so it matches `BIGINT`, `UUID` or `TEXT` column whichever is in use
+impl Encode<'_, Postgres> for Id {
+	fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, BoxDynError> {
+		match self {
+			Id::Int(id) => <i64 as Encode<Postgres>>::encode_by_ref(id, buf),
+			Id::Uuid(id) => <Uuid as Encode<Postgres>>::encode_by_ref(id, buf),
+			Id::Ulid(id) => <String as Encode<Postgres>>::encode(id.to_string(), buf),
+		}
+	}
+
+	fn produces(&self) -> Option<PgTypeInfo> {
+		Some(match self {
+			Id::Int(_) => <i64 as Type<Postgres>>::type_info(),
+			Id::Uuid(_) => <Uuid as Type<Postgres>>::type_info(),
+			Id::Ulid(_) => <String as Type<Postgres>>::type_info(),
+		})
+	}
+}
+
+impl<'r> sqlx::Decode<'r, Postgres> for Id {
+	fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+		let ty = value.type_info().into_owned();
+		if <i64 as Type<Postgres>>::compatible(&ty) {
+			Ok(Id::Int(<i64 as sqlx::Decode<Postgres>>::decode(value)?))
+		} else if <Uuid as Type<Postgres>>::compatible(&ty) {
+			Ok(Id::Uuid(<Uuid as sqlx::Decode<Postgres>>::decode(value)?))
+		} else {
+			Ok(<&str as sqlx::Decode<Postgres>>::decode(value)?.parse::<Id>().map_err(|err| format!("{:?}", err))?)
+		}
+	}
+}
+
+impl sqlx::Type<Postgres> for Id {
+	fn type_info() -> PgTypeInfo {
+		<i64 as Type<Postgres>>::type_info()
+	}
+
+	fn compatible(ty: &PgTypeInfo) -> bool {
+		<i64 as Type<Postgres>>::compatible(ty) || <Uuid as Type<Postgres>>::compatible(ty) || <String as Type<Postgres>>::compatible(ty)
+	}
+}
+
+/// Array of ids bound as array of the type of their variant, for bulk operations. Ids must be of the same variant.
+pub(crate) struct IdArray<'a>(pub &'a [Id]);
+
+impl IdArray<'_> {
+	fn element_type(&self) -> PgTypeInfo {
+		match self.0.first() {
+			Some(Id::Uuid(_)) => <Uuid as PgHasArrayType>::array_type_info(),
+			Some(Id::Ulid(_)) => <String as PgHasArrayType>::array_type_info(),
+			_ => <i64 as PgHasArrayType>::array_type_info(),
+		}
+	}
+}
+
+impl Encode<'_, Postgres> for IdArray<'_> {
+	fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, BoxDynError> {
+		let mixed = || -> BoxDynError { "Ids Of Different Types Can't Be Bound Together".into() };
+		match self.0.first() {
+			Some(Id::Uuid(_)) => self.0.iter().map(|id| id.as_uuid().ok_or_else(mixed)).collect::<Result<Vec<_>, _>>()?.encode(buf),
+			Some(Id::Ulid(_)) => self
+				.0
+				.iter()
+				.map(|id| id.as_ulid().map(|id| id.to_string()).ok_or_else(mixed))
+				.collect::<Result<Vec<_>, _>>()?
+				.encode(buf),
+			_ => self.0.iter().map(|id| id.as_i64().ok_or_else(mixed)).collect::<Result<Vec<_>, _>>()?.encode(buf),
+		}
+	}
+
+	fn produces(&self) -> Option<PgTypeInfo> {
+		Some(self.element_type())
+	}
+}
+
+impl sqlx::Type<Postgres> for IdArray<'_> {
+	fn type_info() -> PgTypeInfo {
+		<i64 as PgHasArrayType>::array_type_info()
+	}
+
+	fn compatible(ty: &PgTypeInfo) -> bool {
+		ty.name().ends_with("[]")
+	}
+}
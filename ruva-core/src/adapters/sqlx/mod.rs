@@ -1,2 +1,6 @@
 pub mod conversion;
+pub mod node;
+pub mod outbox;
 pub mod postgres;
+pub mod snapshot;
+pub mod subscription;
@@ -0,0 +1,200 @@
+//! ### Machine id lease
+//! [MachineIdAllocator] claims machine id of the datacenter that no other instance holds, so that instances
+//! can be scaled out without being given [NodeId] one by one. Claim is a lease that expires unless renewed,
+//! so ids of instances that went away are reclaimed once their lease expires.
+//!
+//! Once the lease is lost or released, [SnowFlake::generate](crate::prelude::SnowFlake::generate) configured with its node
+//! stops generating ids, as other instance may claim the node and generate the same ones.
+//!
+//! ```sql
+//! CREATE TABLE service_machine_id_lease (
+//!     datacenter_id INT NOT NULL,
+//!     machine_id INT NOT NULL,
+//!     holder TEXT NOT NULL,
+//!     expires_at TIMESTAMPTZ NOT NULL,
+//!     PRIMARY KEY (datacenter_id, machine_id)
+//! );
+//! ```
+//!
+//! ```rust,no_run
+//! # use ruva_core::prelude::*;
+//! # async fn run(pool: sqlx::PgPool) -> Result<(), BaseError> {
+//! let lease = MachineIdAllocator::new(pool, 1).acquire().await?;
+//! SnowFlake::configure(lease.node())?;
+//! tokio::spawn({
+//!     let lease = lease.clone();
+//!     async move { lease.keep_alive().await }
+//! });
+//! // ...
+//! lease.release().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::prelude::{BaseError, NodeId, SnowFlake};
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+pub struct MachineIdAllocator {
+	pool: PgPool,
+	datacenter_id: i32,
+	holder: String,
+	ttl: Duration,
+}
+
+impl MachineIdAllocator {
+	/// Allocator for the datacenter. Lease lasts 30 seconds unless renewed.
+	pub fn new(pool: PgPool, datacenter_id: i32) -> Self {
+		Self {
+			pool,
+			datacenter_id,
+			// * Host name and process id alone collide across containers, where both are often the same
+			holder: format!("{}-{}-{}", std::env::var("HOSTNAME").unwrap_or_else(|_| "ruva".to_string()), std::process::id(), uuid::Uuid::new_v4()),
+			ttl: Duration::from_secs(30),
+		}
+	}
+
+	/// Name the lease is held under, host name, process id and random uuid by default.
+	/// It must be unique to the instance, as lease of the same holder is taken as its own.
+	pub fn holder(mut self, holder: impl Into<String>) -> Self {
+		self.holder = holder.into();
+		self
+	}
+
+	pub fn ttl(mut self, ttl: Duration) -> Self {
+		self.ttl = ttl;
+		self
+	}
+
+	/// Claim the lowest machine id that is free or whose lease has expired.
+	pub async fn acquire(self) -> Result<MachineIdLease, BaseError> {
+		NodeId::new(self.datacenter_id, 0)?;
+
+		// * Instance racing for the same id loses on conflict and tries the next free one
+		for _ in 0..=NodeId::MAX_ID {
+			let machine_id: Option<i32> = sqlx::query_scalar(
+				r#"
+                INSERT INTO service_machine_id_lease (datacenter_id, machine_id, holder, expires_at)
+                SELECT $1, candidate.id, $2, NOW() + $3 * INTERVAL '1 millisecond'
+                FROM generate_series(0, $4) AS candidate(id)
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM service_machine_id_lease lease
+                    WHERE lease.datacenter_id = $1 AND lease.machine_id = candidate.id AND lease.expires_at > NOW()
+                )
+                ORDER BY candidate.id
+                LIMIT 1
+                ON CONFLICT (datacenter_id, machine_id) DO UPDATE
+                SET holder = EXCLUDED.holder, expires_at = EXCLUDED.expires_at
+                WHERE service_machine_id_lease.expires_at <= NOW()
+                RETURNING machine_id
+                "#,
+			)
+			.bind(self.datacenter_id)
+			.bind(&self.holder)
+			.bind(self.ttl.as_millis() as i64)
+			.bind(NodeId::MAX_ID)
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|err| BaseError::DatabaseError(err.to_string()))?;
+
+			if let Some(machine_id) = machine_id {
+				return Ok(MachineIdLease {
+					node: NodeId::new(self.datacenter_id, machine_id)?,
+					allocator: self,
+				});
+			}
+			if !self.any_free().await? {
+				break;
+			}
+		}
+		Err(BaseError::InvalidNodeId(format!("no machine id is free in datacenter {}", self.datacenter_id)))
+	}
+
+	async fn any_free(&self) -> Result<bool, BaseError> {
+		sqlx::query_scalar("SELECT COUNT(*) <= $2 FROM service_machine_id_lease WHERE datacenter_id = $1 AND expires_at > NOW()")
+			.bind(self.datacenter_id)
+			.bind(NodeId::MAX_ID as i64)
+			.fetch_one(&self.pool)
+			.await
+			.map_err(|err| BaseError::DatabaseError(err.to_string()))
+	}
+}
+
+/// Machine id held by this instance until the lease expires or is released.
+#[derive(Clone)]
+pub struct MachineIdLease {
+	node: NodeId,
+	allocator: MachineIdAllocator,
+}
+
+impl MachineIdLease {
+	pub fn node(&self) -> NodeId {
+		self.node
+	}
+
+	/// Extend the lease by its ttl. Fails when the lease has been taken over after it expired,
+	/// in which case [SnowFlake] stops generating ids with the node.
+	pub async fn renew(&self) -> Result<(), BaseError> {
+		let renewed = sqlx::query(
+			r#"
+            UPDATE service_machine_id_lease
+            SET expires_at = NOW() + $4 * INTERVAL '1 millisecond'
+            WHERE datacenter_id = $1 AND machine_id = $2 AND holder = $3
+            "#,
+		)
+		.bind(self.node.datacenter_id)
+		.bind(self.node.machine_id)
+		.bind(&self.allocator.holder)
+		.bind(self.allocator.ttl.as_millis() as i64)
+		.execute(&self.allocator.pool)
+		.await
+		.map_err(|err| BaseError::DatabaseError(err.to_string()))?
+		.rows_affected();
+
+		if renewed == 0 {
+			SnowFlake::revoke(self.node);
+			return Err(BaseError::InvalidNodeId(format!("lease on node {} is lost", self.node)));
+		}
+		Ok(())
+	}
+
+	/// Renew the lease every third of its ttl until it is lost, returning the error.
+	/// Renewal failing for other reasons is retried until the lease may have expired, after which [SnowFlake]
+	/// stops generating ids with the node.
+	pub async fn keep_alive(&self) -> BaseError {
+		let mut renewed_at = Instant::now();
+		loop {
+			tokio::time::sleep(self.allocator.ttl / 3).await;
+			// * Lease is extended from about when renewal is sent, not when it returns
+			let renewing_at = Instant::now();
+			match self.renew().await {
+				Ok(()) => renewed_at = renewing_at,
+				Err(err @ BaseError::InvalidNodeId(_)) => {
+					tracing::error!("machine id lease is lost! {:?}", err);
+					return err;
+				}
+				Err(err) if renewed_at.elapsed() >= self.allocator.ttl => {
+					tracing::error!("failed to renew machine id lease before it expires! {:?}", err);
+					SnowFlake::revoke(self.node);
+					return err;
+				}
+				Err(err) => tracing::warn!("failed to renew machine id lease! {:?} Retrying", err),
+			}
+		}
+	}
+
+	/// Give the machine id back so that other instance can claim it right away.
+	/// [SnowFlake] stops generating ids with the node, as they may collide with ones of the next holder.
+	pub async fn release(&self) -> Result<(), BaseError> {
+		SnowFlake::revoke(self.node);
+		sqlx::query("DELETE FROM service_machine_id_lease WHERE datacenter_id = $1 AND machine_id = $2 AND holder = $3")
+			.bind(self.node.datacenter_id)
+			.bind(self.node.machine_id)
+			.bind(&self.allocator.holder)
+			.execute(&self.allocator.pool)
+			.await
+			.map_err(|err| BaseError::DatabaseError(err.to_string()))?;
+		Ok(())
+	}
+}
@@ -0,0 +1,433 @@
+//! ### Outbox consumer
+//! [OutBoxConsumer] relays outboxes saved in `service_outbox` to [TOutBoxPublisher].
+//!
+//! It listens on the channel notified when outboxes are saved(see [ContextManager::with_outbox_channel]) so that
+//! they are relayed as soon as the transaction commits. As notifications may be lost, for example while reconnecting,
+//! the table is also polled at given interval.
+//!
+//! ### Outbox retention
+//! [OutBoxRetention] removes outboxes older than given age so that `service_outbox` doesn't grow without limit.
+//! Rows are deleted in batches, each in its own transaction, not to hold locks for long.
+//! They can be archived to history table or JSON Lines file before deletion.
+//!
+//! As `service_outbox` is also the event store, outboxes that are still to be read from it are never removed:
+//! - ones not yet passed by every subscription with checkpoint in `service_subscription_checkpoint`, if the table exists.
+//!   Checkpoint of subscription that is no longer used must be deleted for the outboxes to be removed.
+//! - ones of event-sourced aggregates given with [OutBoxRetention::keep_event_sourced], which are replayed to rebuild them.
+//!
+//! [ContextManager::with_outbox_channel]: crate::prelude::ContextManager::with_outbox_channel
+
+use super::conversion::IdArray;
+use crate::prelude::{BaseError, Id, OutBox, TAggregate, TEventSourced, TOutBoxPublisher};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgListener, PgRow};
+use sqlx::{PgPool, Row};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub struct OutBoxConsumer {
+	pool: PgPool,
+	channel: String,
+	poll_interval: Duration,
+	batch_size: i64,
+	parallelism: usize,
+}
+
+impl OutBoxConsumer {
+	pub fn new(pool: PgPool, channel: impl Into<String>) -> Self {
+		Self {
+			pool,
+			channel: channel.into(),
+			poll_interval: Duration::from_secs(5),
+			batch_size: 100,
+			parallelism: 8,
+		}
+	}
+
+	/// Interval at which the table is polled when no notification arrives.
+	pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+		self.poll_interval = poll_interval;
+		self
+	}
+
+	pub fn batch_size(mut self, batch_size: i64) -> Self {
+		self.batch_size = batch_size;
+		self
+	}
+
+	/// Number of partitions published at the same time.
+	pub fn parallelism(mut self, parallelism: usize) -> Self {
+		self.parallelism = parallelism.max(1);
+		self
+	}
+
+	/// Relay outboxes until the future is dropped.
+	/// Failure of relaying is logged and retried on the next wake-up.
+	pub async fn run(&self, publisher: &impl TOutBoxPublisher) -> Result<(), BaseError> {
+		let mut listener = PgListener::connect_with(&self.pool).await?;
+		listener.listen(&self.channel).await?;
+
+		loop {
+			if let Err(err) = self.process_pending(publisher).await {
+				tracing::error!("failed to relay outboxes! {:?}", err);
+			}
+
+			match tokio::time::timeout(self.poll_interval, listener.recv()).await {
+				Ok(Ok(_notification)) => (),
+				Ok(Err(err)) => {
+					// * Listener reconnects on the next `recv`, notifications sent meanwhile are picked up by polling
+					tracing::warn!("failed to receive outbox notification! {}", err);
+					tokio::time::sleep(self.poll_interval).await;
+				}
+				// * Poll in case notification was lost
+				Err(_elapsed) => (),
+			}
+		}
+	}
+
+	/// Relay unprocessed outboxes until none is left, running partitions in parallel.
+	/// Within a partition, outboxes are published in the order they were committed, even across aggregates.
+	///
+	/// Returns the number of outboxes relayed. When publishing fails, the partition stops there so that nothing
+	/// is published ahead of the failed outbox, while other partitions go on. The first error is returned at the end.
+	pub async fn process_pending(&self, publisher: &impl TOutBoxPublisher) -> Result<usize, BaseError> {
+		let mut processed = 0;
+		let mut stopped: Vec<String> = vec![];
+		let mut error = None;
+		loop {
+			let partitions: Vec<String> = sqlx::query_scalar("SELECT DISTINCT partition_key FROM service_outbox WHERE processed = false AND NOT (partition_key = ANY($1))")
+				.bind(&stopped)
+				.fetch_all(&self.pool)
+				.await?;
+
+			let results = futures::stream::iter(partitions)
+				.map(|partition| async move {
+					let res = self.process_partition(&partition, publisher).await;
+					(partition, res)
+				})
+				.buffer_unordered(self.parallelism)
+				.collect::<Vec<_>>()
+				.await;
+
+			// * Partitions locked by other consumers make no progress, which ends the loop as well
+			let mut progressed = false;
+			for (partition, res) in results {
+				match res {
+					Ok(count) => {
+						processed += count;
+						progressed |= count > 0;
+					}
+					Err(err) => {
+						tracing::error!("failed to relay outboxes of partition {}! {:?}", partition, err);
+						stopped.push(partition);
+						error.get_or_insert(err);
+					}
+				}
+			}
+			if !progressed {
+				break;
+			}
+		}
+
+		match error {
+			Some(err) => Err(err),
+			None => Ok(processed),
+		}
+	}
+
+	async fn process_partition(&self, partition_key: &str, publisher: &impl TOutBoxPublisher) -> Result<usize, BaseError> {
+		let mut trx = self.pool.begin().await?;
+
+		// * Only one consumer relays a partition at a time not to reorder it
+		let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_xact_lock(hashtextextended('service_outbox:' || $1, 0))")
+			.bind(partition_key)
+			.fetch_one(&mut *trx)
+			.await?;
+		if !acquired {
+			return Ok(0);
+		}
+
+		let outboxes = sqlx::query_as::<_, OutBox>(
+			r#"
+            SELECT id, aggregate_id, aggregate_name, topic, state, content_type, processed, create_dt, sequence, partition_key
+            FROM service_outbox
+            WHERE processed = false AND partition_key = $1
+            ORDER BY partition_sequence, aggregate_name, aggregate_id, sequence
+            LIMIT $2
+            "#,
+		)
+		.bind(partition_key)
+		.bind(self.batch_size)
+		.fetch_all(&mut *trx)
+		.await?;
+
+		let mut published = Vec::with_capacity(outboxes.len());
+		let mut res = Ok(outboxes.len());
+		for outbox in outboxes.iter() {
+			if let Err(err) = publisher.publish(outbox).await {
+				res = Err(err);
+				break;
+			}
+			published.push(outbox.id);
+		}
+
+		if !published.is_empty() {
+			sqlx::query("UPDATE service_outbox SET processed = true WHERE id = ANY($1)")
+				.bind(IdArray(&published))
+				.execute(&mut *trx)
+				.await?;
+		}
+		trx.commit().await?;
+		res
+	}
+}
+
+impl<'r> sqlx::FromRow<'r, PgRow> for OutBox {
+	fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+		Ok(Self {
+			id: row.try_get("id")?,
+			aggregate_id: row.try_get("aggregate_id")?,
+			aggregate_name: row.try_get("aggregate_name")?,
+			topic: row.try_get("topic")?,
+			state: row.try_get("state")?,
+			content_type: row.try_get("content_type")?,
+			processed: row.try_get("processed")?,
+			create_dt: row.try_get("create_dt")?,
+			sequence: row.try_get("sequence")?,
+			partition_key: row.try_get("partition_key")?,
+		})
+	}
+}
+
+/// Outbox as archived, along with its position in the event stream and in its partition
+/// that subscriptions and partitioned consumers rely on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedOutBox {
+	#[serde(flatten)]
+	pub outbox: OutBox,
+	pub transaction_id: i64,
+	pub partition_sequence: i64,
+}
+
+impl<'r> sqlx::FromRow<'r, PgRow> for ArchivedOutBox {
+	fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+		Ok(Self {
+			outbox: OutBox::from_row(row)?,
+			transaction_id: row.try_get("transaction_id")?,
+			partition_sequence: row.try_get("partition_sequence")?,
+		})
+	}
+}
+
+/// Where outboxes are archived to before deletion.
+#[derive(Debug, Clone)]
+pub enum OutBoxArchive {
+	/// Table with the same columns as `service_outbox`. It is created if it doesn't exist.
+	HistoryTable(String),
+	/// File each outbox is appended to as a JSON line of [ArchivedOutBox].
+	JsonLines(PathBuf),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+	pub archived: u64,
+	pub deleted: u64,
+	pub batches: u64,
+}
+
+pub struct OutBoxRetention {
+	pool: PgPool,
+	older_than: Duration,
+	processed_only: bool,
+	batch_size: i64,
+	archive: Option<OutBoxArchive>,
+	kept_aggregates: Vec<String>,
+}
+
+impl OutBoxRetention {
+	/// Retain outboxes for the given age. By default, only processed ones are removed.
+	pub fn new(pool: PgPool, older_than: Duration) -> Self {
+		Self {
+			pool,
+			older_than,
+			processed_only: true,
+			batch_size: 1000,
+			archive: None,
+			kept_aggregates: vec![],
+		}
+	}
+
+	/// Keep every outbox of the event-sourced aggregate, as it is rebuilt by replaying them.
+	pub fn keep_event_sourced<A: TEventSourced + TAggregate>(mut self) -> Self {
+		self.kept_aggregates.push(A::aggregate_name().to_string());
+		self
+	}
+
+	/// Remove unprocessed outboxes as well, giving up on relaying them.
+	pub fn include_unprocessed(mut self) -> Self {
+		self.processed_only = false;
+		self
+	}
+
+	pub fn batch_size(mut self, batch_size: i64) -> Self {
+		self.batch_size = batch_size;
+		self
+	}
+
+	pub fn archive(mut self, archive: OutBoxArchive) -> Self {
+		self.archive = Some(archive);
+		self
+	}
+
+	/// Remove outboxes past retention, archiving them first if archive is given.
+	pub async fn run(&self) -> Result<RetentionReport, BaseError> {
+		let older_than = chrono::Duration::from_std(self.older_than).map_err(|err| BaseError::InvalidConfiguration(format!("Retention Out Of Range: {}", err)))?;
+		let cutoff = Utc::now() - older_than;
+
+		if let Some(OutBoxArchive::HistoryTable(table)) = &self.archive {
+			validate_identifier(table)?;
+			sqlx::query(&format!("CREATE TABLE IF NOT EXISTS {table} (LIKE service_outbox INCLUDING DEFAULTS)"))
+				.execute(&self.pool)
+				.await?;
+		}
+
+		// * Subscriptions are optional, so their checkpoints are looked at only if the table is there
+		let has_subscriptions: bool = sqlx::query_scalar("SELECT to_regclass('service_subscription_checkpoint') IS NOT NULL").fetch_one(&self.pool).await?;
+
+		let mut report = RetentionReport::default();
+		loop {
+			let deleted = self.purge_batch(cutoff, has_subscriptions).await?;
+			if deleted == 0 {
+				break;
+			}
+			report.batches += 1;
+			report.deleted += deleted;
+			if self.archive.is_some() {
+				report.archived += deleted;
+			}
+			if deleted < self.batch_size as u64 {
+				break;
+			}
+		}
+		tracing::info!("outbox retention done! {:?}", report);
+		Ok(report)
+	}
+
+	async fn purge_batch(&self, cutoff: DateTime<Utc>, has_subscriptions: bool) -> Result<u64, BaseError> {
+		let mut trx = self.pool.begin().await?;
+
+		// * Transaction of the oldest checkpoint may have outboxes left to read, so it is kept as a whole
+		let unread_by_subscription = match has_subscriptions {
+			true => "AND transaction_id < COALESCE((SELECT MIN(transaction_id) FROM service_subscription_checkpoint), transaction_id + 1)",
+			false => "",
+		};
+		let mut outboxes = sqlx::query_as::<_, ArchivedOutBox>(&format!(
+			r#"
+            DELETE FROM service_outbox
+            WHERE id IN (
+                SELECT id FROM service_outbox
+                WHERE create_dt < $1 AND (processed OR NOT $2) AND aggregate_name <> ALL($4)
+                {unread_by_subscription}
+                ORDER BY id
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, aggregate_id, aggregate_name, topic, state, content_type, processed, create_dt, sequence, partition_key,
+                transaction_id, partition_sequence
+            "#,
+		))
+		.bind(cutoff)
+		.bind(self.processed_only)
+		.bind(self.batch_size)
+		.bind(&self.kept_aggregates)
+		.fetch_all(&mut *trx)
+		.await?;
+
+		match &self.archive {
+			Some(OutBoxArchive::HistoryTable(table)) if !outboxes.is_empty() => {
+				crate::prepare_bulk_operation!(&outboxes, transaction_id: i64, partition_sequence: i64);
+				let archived = outboxes.iter().map(|archived| &archived.outbox).collect::<Vec<_>>();
+				crate::prepare_bulk_operation!(
+					&archived,
+					id: Id,
+					aggregate_id: String,
+					aggregate_name: String,
+					topic: String,
+					state: Vec<u8>,
+					content_type: String,
+					processed: bool,
+					create_dt: DateTime<Utc>,
+					sequence: i64,
+					partition_key: String
+				);
+				sqlx::query(&format!(
+					r#"
+                    INSERT INTO {table}
+                        (id, aggregate_id, aggregate_name, topic, state, content_type, processed, create_dt, sequence, partition_key,
+                         transaction_id, partition_sequence)
+                    SELECT * FROM UNNEST
+                        ($1, $2::text[], $3::text[], $4::text[], $5::BYTEA[], $6::text[], $7::BOOLEAN[], $8::TIMESTAMPTZ[], $9::BIGINT[], $10::text[],
+                         $11::BIGINT[], $12::BIGINT[])
+                    "#
+				))
+				.bind(IdArray(&id))
+				.bind(&aggregate_id)
+				.bind(&aggregate_name)
+				.bind(&topic)
+				.bind(&state)
+				.bind(&content_type)
+				.bind(&processed)
+				.bind(&create_dt)
+				.bind(&sequence)
+				.bind(&partition_key)
+				.bind(&transaction_id)
+				.bind(&partition_sequence)
+				.execute(&mut *trx)
+				.await?;
+			}
+			// * Written before commit so that outboxes are never lost. If commit fails, they may be archived twice.
+			Some(OutBoxArchive::JsonLines(path)) if !outboxes.is_empty() => {
+				let (path, taken) = (path.clone(), std::mem::take(&mut outboxes));
+				// * File is written and synced on blocking thread not to stall the runtime
+				outboxes = tokio::task::spawn_blocking(move || append_json_lines(&path, &taken).map(|_| taken))
+					.await
+					.map_err(|err| BaseError::ArchiveError(err.to_string()))?
+					.inspect_err(|err| tracing::error!("failed to archive outboxes! {:?}", err))?;
+			}
+			_ => (),
+		}
+
+		trx.commit().await?;
+		Ok(outboxes.len() as u64)
+	}
+}
+
+fn append_json_lines(path: &PathBuf, outboxes: &[ArchivedOutBox]) -> Result<(), BaseError> {
+	let io_error = |err: std::io::Error| BaseError::ArchiveError(format!("{}: {}", path.display(), err));
+
+	let mut file = std::io::BufWriter::new(std::fs::OpenOptions::new().create(true).append(true).open(path).map_err(io_error)?);
+	for outbox in outboxes {
+		serde_json::to_writer(&mut file, outbox).map_err(|err| match err.is_io() {
+			true => io_error(err.into()),
+			false => BaseError::CodecError(err.to_string()),
+		})?;
+		file.write_all(b"\n").map_err(io_error)?;
+	}
+	file.flush().map_err(io_error)?;
+	file.get_ref().sync_data().map_err(io_error)
+}
+
+// * Table name is interpolated into query, so only plain(optionally schema qualified) identifiers are allowed
+fn validate_identifier(name: &str) -> Result<(), BaseError> {
+	let valid = name
+		.split('.')
+		.all(|part| !part.is_empty() && !part.starts_with(|c: char| c.is_ascii_digit()) && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+	if !valid {
+		tracing::error!("Invalid Table Name: {}", name);
+		return Err(BaseError::InvalidConfiguration(format!("Invalid Table Name: {}", name)));
+	}
+	Ok(())
+}
@@ -1,9 +1,12 @@
+use super::conversion::IdArray;
 use crate::bus_components::contexts::Context;
 use crate::{
-	prelude::{BaseError, TUnitOfWork},
+	prelude::{AggregateLockKey, BaseError, Id, OutBox, TAggregate, TUnitOfWork},
 	prepare_bulk_operation,
 };
+use chrono::{DateTime, Utc};
 use sqlx::{PgConnection, PgPool};
+use std::collections::{BTreeMap, HashMap};
 
 impl Context {
 	pub fn transaction(&mut self) -> &mut PgConnection {
@@ -13,63 +16,239 @@ impl Context {
 		}
 	}
 
+	/// Run handlers registered to join the transaction on internally notifiable events collected so far.
+	/// Events raised while handling them are appended to the current events and handled in turn.
+	pub(crate) async fn run_joined_event_handlers(&mut self) -> Result<(), BaseError> {
+		let Some(joined_event_handler) = self.super_ctx.joined_event_handler else {
+			return Ok(());
+		};
+
+		let mut idx = 0;
+		while let Some(event) = self.curr_events.get(idx).cloned() {
+			idx += 1;
+			if !event.internally_notifiable() {
+				continue;
+			}
+			if let Some(handlers) = joined_event_handler.get(&event.metadata().topic) {
+				for handler in handlers {
+					handler(event.clone(), self).await?;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Lock aggregate until the transaction ends, waiting for other transaction holding the lock to end.
+	/// Lock is released on commit or rollback, not by nested unit of work.
+	pub async fn lock_aggregate<A: TAggregate>(&mut self, id: impl Into<AggregateLockKey>) -> Result<(), BaseError> {
+		sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1, 0))")
+			.bind(advisory_lock_key(A::aggregate_name(), &id.into()))
+			.execute(self.transaction())
+			.await?;
+		Ok(())
+	}
+
+	/// Lock aggregate until the transaction ends without waiting.
+	/// If the lock is held by other transaction, `BaseError::AggregateLocked` is returned.
+	pub async fn try_lock_aggregate<A: TAggregate>(&mut self, id: impl Into<AggregateLockKey>) -> Result<(), BaseError> {
+		let key = id.into();
+		let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_xact_lock(hashtextextended($1, 0))")
+			.bind(advisory_lock_key(A::aggregate_name(), &key))
+			.fetch_one(self.transaction())
+			.await?;
+		if !acquired {
+			return Err(BaseError::AggregateLocked {
+				aggregate_name: A::aggregate_name().to_string(),
+				aggregate_id: key.0,
+			});
+		}
+		Ok(())
+	}
+
 	pub(crate) async fn save_outbox(&mut self) -> Result<(), BaseError> {
-		let outboxes = self.curr_events.iter().filter(|e| e.externally_notifiable()).map(|o| o.outbox()).collect::<Vec<_>>();
+		let mut outboxes = self
+			.curr_events
+			.iter()
+			.filter(|e| e.externally_notifiable())
+			.map(|e| self.super_ctx.outbox(e.as_ref()))
+			.collect::<Result<Vec<_>, _>>()?;
+		if outboxes.is_empty() {
+			return Ok(());
+		}
+		let partition_sequence = self.assign_outbox_sequences(&mut outboxes).await?;
 
 		prepare_bulk_operation!(
 			&outboxes,
-			id: i64,
+			id: Id,
 			aggregate_id: String,
 			aggregate_name:String,
 			topic: String,
-			state: String
+			state: Vec<u8>,
+			content_type: String,
+			sequence: i64,
+			partition_key: String,
+			create_dt: DateTime<Utc>
 		);
 		sqlx::query(
 			r#"
             INSERT INTO service_outbox
-                (id, aggregate_id, topic, state, aggregate_name)
+                (id, aggregate_id, topic, state, aggregate_name, content_type, sequence, partition_key, create_dt, partition_sequence)
             SELECT * FROM UNNEST
-                ($1::BIGINT[], $2::text[],  $3::text[], $4::text[], $5::text[])
+                ($1, $2::text[],  $3::text[], $4::BYTEA[], $5::text[], $6::text[], $7::BIGINT[], $8::text[], $9::TIMESTAMPTZ[], $10::BIGINT[])
             "#,
 		)
-		.bind(&id)
+		.bind(IdArray(&id))
 		.bind(&aggregate_id)
 		.bind(&topic)
 		.bind(&state)
 		.bind(&aggregate_name)
+		.bind(&content_type)
+		.bind(&sequence)
+		.bind(&partition_key)
+		.bind(&create_dt)
+		.bind(&partition_sequence)
 		.execute(self.transaction())
 		.await
 		.map_err(|err| {
 			tracing::error!("failed to insert outbox! {}", err);
 			BaseError::DatabaseError(err.to_string())
 		})?;
+
+		// * Notification is delivered only when the transaction commits
+		if let Some(channel) = self.super_ctx.outbox_channel.clone() {
+			sqlx::query("SELECT pg_notify($1, '')").bind(channel).execute(self.transaction()).await?;
+		}
 		Ok(())
 	}
 }
 
+impl Context {
+	/// Assign sequences per aggregate and per partition by advancing counters in `service_outbox_sequence`
+	/// and `service_outbox_partition_sequence`, returning partition sequences in the order of outboxes.
+	/// Counter rows stay locked until the transaction ends, so sequences follow commit order of the same aggregate or partition.
+	/// Aggregate counters are always taken before partition ones, avoiding deadlock between the two.
+	async fn assign_outbox_sequences(&mut self, outboxes: &mut [OutBox]) -> Result<Vec<i64>, BaseError> {
+		let aggregates = outboxes.iter().map(|outbox| (outbox.aggregate_name.clone(), outbox.aggregate_id.clone())).collect::<Vec<_>>();
+		let counts = count_keys(&aggregates);
+		let (keys, count): (Vec<_>, Vec<_>) = counts.iter().map(|(aggregate, count)| (aggregate.clone(), *count)).unzip();
+		let (aggregate_name, aggregate_id): (Vec<_>, Vec<_>) = keys.into_iter().unzip();
+
+		let last_sequences = sqlx::query_as::<_, (String, String, i64)>(
+			r#"
+            INSERT INTO service_outbox_sequence AS s
+                (aggregate_name, aggregate_id, last_sequence)
+            SELECT * FROM UNNEST
+                ($1::text[], $2::text[], $3::BIGINT[])
+            ON CONFLICT (aggregate_name, aggregate_id)
+            DO UPDATE SET last_sequence = s.last_sequence + EXCLUDED.last_sequence
+            RETURNING aggregate_name, aggregate_id, last_sequence
+            "#,
+		)
+		.bind(&aggregate_name)
+		.bind(&aggregate_id)
+		.bind(&count)
+		.fetch_all(self.transaction())
+		.await?;
+		let sequences = next_sequences(&aggregates, &counts, last_sequences.into_iter().map(|(name, id, last_sequence)| ((name, id), last_sequence)));
+		for (outbox, sequence) in outboxes.iter_mut().zip(sequences) {
+			outbox.sequence = sequence;
+		}
+
+		let partitions = outboxes.iter().map(|outbox| outbox.partition_key.clone()).collect::<Vec<_>>();
+		let counts = count_keys(&partitions);
+		let (partition_key, count): (Vec<_>, Vec<_>) = counts.iter().map(|(partition, count)| (partition.clone(), *count)).unzip();
+		let last_sequences = sqlx::query_as::<_, (String, i64)>(
+			r#"
+            INSERT INTO service_outbox_partition_sequence AS s
+                (partition_key, last_sequence)
+            SELECT * FROM UNNEST
+                ($1::text[], $2::BIGINT[])
+            ON CONFLICT (partition_key)
+            DO UPDATE SET last_sequence = s.last_sequence + EXCLUDED.last_sequence
+            RETURNING partition_key, last_sequence
+            "#,
+		)
+		.bind(&partition_key)
+		.bind(&count)
+		.fetch_all(self.transaction())
+		.await?;
+		Ok(next_sequences(&partitions, &counts, last_sequences))
+	}
+}
+
+// * Sorted so that counters are locked in the same order across transactions, avoiding deadlock
+fn count_keys<K: Ord + Clone>(keys: &[K]) -> BTreeMap<K, i64> {
+	let mut counts = BTreeMap::new();
+	for key in keys {
+		*counts.entry(key.clone()).or_default() += 1;
+	}
+	counts
+}
+
+/// Sequence of each key, given out in the order they were raised from the first of those just counted.
+fn next_sequences<K: Ord + Clone + std::hash::Hash>(keys: &[K], counts: &BTreeMap<K, i64>, last_sequences: impl IntoIterator<Item = (K, i64)>) -> Vec<i64> {
+	let mut next_sequences = last_sequences
+		.into_iter()
+		.map(|(key, last_sequence)| {
+			let next_sequence = last_sequence - counts[&key] + 1;
+			(key, next_sequence)
+		})
+		.collect::<HashMap<_, _>>();
+	keys.iter()
+		.map(|key| {
+			let next_sequence = next_sequences.get_mut(key).expect("sequence must be assigned");
+			*next_sequence += 1;
+			*next_sequence - 1
+		})
+		.collect()
+}
+
 impl TUnitOfWork for Context {
+	/// Begin transaction. If transaction has begun already, savepoint is set instead so nested unit of work
+	/// can be committed or rolled back without affecting the outer one.
 	async fn begin(&mut self) -> Result<(), BaseError> {
 		match self.pg_transaction.as_mut() {
 			None => {
-				let trx = self.super_ctx.conn;
+				let conn = self.super_ctx.connection()?;
 
-				if let Some(trx) = trx.downcast_ref::<&PgPool>().or(trx.downcast_ref::<PgPool>().as_ref()) {
-					self.pg_transaction = Some(trx.begin().await?);
+				if let Some(pool) = conn.downcast_ref::<PgPool>() {
+					let mut trx = pool.begin().await?;
+					if let Some(set_transaction) = self.transaction_options.to_sql() {
+						sqlx::query(&set_transaction).execute(&mut *trx).await?;
+					}
+					self.pg_transaction = Some(trx);
 				} else {
 					tracing::error!("Transaction Error!");
 					return Err(BaseError::TransactionError);
 				}
-				// simplify above
 
 				Ok(())
 			}
-			Some(_trx) => {
-				tracing::warn!("Transaction Begun Already!");
-				Err(BaseError::TransactionError)?
+			Some(trx) => {
+				let savepoint = savepoint_name(self.savepoints.len());
+				sqlx::query(&format!("SAVEPOINT {savepoint}")).execute(&mut **trx).await?;
+				self.savepoints.push(self.curr_events.len());
+				Ok(())
 			}
 		}
 	}
 
+	/// Commit of nested unit of work only releases its savepoint.
+	/// Events collected in it are kept and processed when the outermost unit of work commits.
+	async fn commit(&mut self) -> Result<(), BaseError> {
+		if self.savepoints.is_empty() {
+			self.run_joined_event_handlers().await?;
+			self.process_internal_events().await?;
+			self.process_external_events().await?;
+			return self._commit().await;
+		}
+
+		let savepoint = savepoint_name(self.savepoints.len() - 1);
+		sqlx::query(&format!("RELEASE SAVEPOINT {savepoint}")).execute(self.transaction()).await?;
+		self.savepoints.pop();
+		Ok(())
+	}
+
 	async fn _commit(&mut self) -> Result<(), BaseError> {
 		match self.pg_transaction.take() {
 			None => panic!("Tranasction Has Not Begun!"),
@@ -77,14 +256,35 @@ impl TUnitOfWork for Context {
 		}
 	}
 
+	/// Rollback of nested unit of work rolls back to its savepoint, discarding events collected since.
 	async fn rollback(&mut self) -> Result<(), BaseError> {
+		if let Some(&event_count) = self.savepoints.last() {
+			let savepoint = savepoint_name(self.savepoints.len() - 1);
+			sqlx::query(&format!("ROLLBACK TO SAVEPOINT {savepoint}")).execute(self.transaction()).await?;
+			self.savepoints.pop();
+			self.curr_events.truncate(event_count);
+			return Ok(());
+		}
+
 		self.curr_events.clear();
 		match self.pg_transaction.take() {
 			None => panic!("Tranasction Has Not Begun!"),
 			Some(trx) => Ok(trx.rollback().await?),
 		}
 	}
+	async fn rollback_nested(&mut self) -> Result<(), BaseError> {
+		let Some(&event_count) = self.savepoints.first() else {
+			return Ok(());
+		};
+		tracing::warn!("{} nested unit(s) of work left open are rolled back", self.savepoints.len());
+		sqlx::query(&format!("ROLLBACK TO SAVEPOINT {}", savepoint_name(0))).execute(self.transaction()).await?;
+		self.savepoints.clear();
+		self.curr_events.truncate(event_count);
+		Ok(())
+	}
+
 	async fn close(&mut self) {
+		self.savepoints.clear();
 		match self.pg_transaction.take() {
 			None => (),
 			Some(trx) => {
@@ -103,3 +303,14 @@ impl TUnitOfWork for Context {
 		Ok(())
 	}
 }
+
+/// Argument of advisory lock query, which hashes aggregate name and its id together into the 64-bit key.
+/// [SnowFlake](crate::prelude::SnowFlake) takes up all 64 bits on its own, so it is hashed as well rather than used as the key,
+/// as it couldn't be told apart from the same id of other aggregate otherwise.
+fn advisory_lock_key(aggregate_name: &str, key: &AggregateLockKey) -> String {
+	format!("{aggregate_name}:{key}")
+}
+
+fn savepoint_name(depth: usize) -> String {
+	format!("ruva_savepoint_{depth}")
+}
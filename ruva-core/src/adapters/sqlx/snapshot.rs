@@ -0,0 +1,139 @@
+//! ### Aggregate snapshot
+//! [SnapshotStore] loads [TEventSourced] aggregate from the latest snapshot of its `{Name}Adapter`,
+//! applying only the events recorded after it.
+//!
+//! New snapshot is taken while loading once [TEventSourced::snapshot_frequency] events have been applied since the last one.
+//! Only the latest snapshot of each aggregate is kept. Snapshots taken with different [TEventSourced::schema_version]
+//! are ignored, and are replaced when the aggregate is loaded next time by the current or later schema version,
+//! so that instance of older one running alongside doesn't take it back.
+//!
+//! ```sql
+//! CREATE TABLE service_aggregate_snapshot (
+//!     aggregate_name TEXT NOT NULL,
+//!     aggregate_id TEXT NOT NULL,
+//!     version BIGINT NOT NULL,
+//!     schema_version INT NOT NULL,
+//!     state JSONB NOT NULL,
+//!     create_dt TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+//!     PRIMARY KEY (aggregate_name, aggregate_id)
+//! );
+//! ```
+
+use crate::prelude::{BaseError, OutBox, TAdaptable, TEventSourced};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::PgPool;
+
+/// Aggregate with the sequence of the last event applied to it.
+#[derive(Debug)]
+pub struct Versioned<A> {
+	pub aggregate: A,
+	pub version: i64,
+}
+
+pub struct SnapshotStore {
+	pool: PgPool,
+}
+
+impl SnapshotStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+
+	/// Rebuild aggregate from the latest snapshot and the events after it.
+	/// Aggregate without snapshot nor event is loaded as default with version 0.
+	/// Snapshot is taken from copy of the aggregate, so fields not kept in adapter are returned as events left them.
+	pub async fn load<A>(&self, aggregate_id: impl ToString) -> Result<Versioned<A>, BaseError>
+	where
+		A: TEventSourced + Clone,
+		A::Adapter: Serialize + DeserializeOwned,
+	{
+		let aggregate_id = aggregate_id.to_string();
+		let (mut aggregate, snapshot_version) = match self.latest::<A>(&aggregate_id).await? {
+			Some(Versioned { aggregate, version }) => (aggregate, version),
+			None => (A::default(), 0),
+		};
+
+		let outboxes = sqlx::query_as::<_, OutBox>(
+			r#"
+            SELECT id, aggregate_id, aggregate_name, topic, state, content_type, processed, create_dt, sequence, partition_key
+            FROM service_outbox
+            WHERE aggregate_name = $1 AND aggregate_id = $2 AND sequence > $3
+            ORDER BY sequence
+            "#,
+		)
+		.bind(A::aggregate_name())
+		.bind(&aggregate_id)
+		.bind(snapshot_version)
+		.fetch_all(&self.pool)
+		.await?;
+
+		let mut version = snapshot_version;
+		for outbox in outboxes.iter() {
+			aggregate.apply(outbox)?;
+			version = outbox.sequence;
+		}
+
+		if version - snapshot_version >= A::snapshot_frequency() {
+			self.save::<A>(&aggregate_id, version, &A::Adapter::from(aggregate.clone())).await?;
+		}
+		Ok(Versioned { aggregate, version })
+	}
+
+	/// Latest snapshot of the current schema version, if any.
+	pub async fn latest<A>(&self, aggregate_id: &str) -> Result<Option<Versioned<A>>, BaseError>
+	where
+		A: TEventSourced,
+		A::Adapter: DeserializeOwned,
+	{
+		let snapshot: Option<(i64, serde_json::Value)> =
+			sqlx::query_as("SELECT version, state FROM service_aggregate_snapshot WHERE aggregate_name = $1 AND aggregate_id = $2 AND schema_version = $3")
+				.bind(A::aggregate_name())
+				.bind(aggregate_id)
+				.bind(A::schema_version())
+				.fetch_optional(&self.pool)
+				.await?;
+
+		snapshot
+			.map(|(version, state)| {
+				let adapter: A::Adapter = serde_json::from_value(state).map_err(|err| BaseError::CodecError(err.to_string()))?;
+				Ok(Versioned { aggregate: adapter.into(), version })
+			})
+			.transpose()
+	}
+
+	/// Save snapshot unless the one saved already is of later schema version, or of the same schema version and later version.
+	pub async fn save<A>(&self, aggregate_id: &str, version: i64, adapter: &<A as TAdaptable>::Adapter) -> Result<(), BaseError>
+	where
+		A: TEventSourced,
+		A::Adapter: Serialize,
+	{
+		let state = serde_json::to_value(adapter).map_err(|err| BaseError::CodecError(err.to_string()))?;
+		sqlx::query(
+			r#"
+            INSERT INTO service_aggregate_snapshot AS s (aggregate_name, aggregate_id, version, schema_version, state)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (aggregate_name, aggregate_id)
+            DO UPDATE SET version = EXCLUDED.version, schema_version = EXCLUDED.schema_version, state = EXCLUDED.state, create_dt = NOW()
+            WHERE s.schema_version < EXCLUDED.schema_version OR (s.schema_version = EXCLUDED.schema_version AND s.version < EXCLUDED.version)
+            "#,
+		)
+		.bind(A::aggregate_name())
+		.bind(aggregate_id)
+		.bind(version)
+		.bind(A::schema_version())
+		.bind(state)
+		.execute(&self.pool)
+		.await?;
+		Ok(())
+	}
+
+	/// Delete snapshots of the aggregate taken with schema version other than the current one, returning the number deleted.
+	pub async fn invalidate<A: TEventSourced>(&self) -> Result<u64, BaseError> {
+		let res = sqlx::query("DELETE FROM service_aggregate_snapshot WHERE aggregate_name = $1 AND schema_version <> $2")
+			.bind(A::aggregate_name())
+			.bind(A::schema_version())
+			.execute(&self.pool)
+			.await?;
+		Ok(res.rows_affected())
+	}
+}
@@ -0,0 +1,231 @@
+//! ### Event subscription
+//! [EventSubscription] feeds events stored in `service_outbox` to in-process [TEventSubscriber] from any position,
+//! reading history first and then following new events as they are committed.
+//!
+//! Stream is ordered by [StreamPosition], the id of the transaction that saved the event followed by its [Id].
+//! Events are read only up to the oldest transaction still in progress, so an event committed late by a transaction
+//! that started earlier can never fall behind the position already read. As catch-up and live mode read the stream
+//! with the same query and the channel is listened to before reading, switching from one to the other neither skips nor repeats events.
+//!
+//! Position of each subscription is saved in `service_subscription_checkpoint` after every batch, so delivery is at-least-once.
+//!
+//! ```sql
+//! ALTER TABLE service_outbox ADD COLUMN transaction_id BIGINT NOT NULL DEFAULT pg_current_xact_id()::TEXT::BIGINT;
+//! CREATE INDEX service_outbox_stream ON service_outbox (transaction_id, id);
+//! CREATE TABLE service_subscription_checkpoint (
+//!     subscription TEXT PRIMARY KEY,
+//!     transaction_id BIGINT NOT NULL,
+//!     outbox_id BIGINT NOT NULL, -- of the type of `service_outbox.id`
+//!     update_dt TIMESTAMPTZ NOT NULL DEFAULT NOW()
+//! );
+//! ```
+
+use crate::prelude::{BaseError, Id, OutBox};
+use sqlx::postgres::PgListener;
+use sqlx::{FromRow, PgPool, Row};
+use std::time::Duration;
+
+/// In-process consumer of stored events.
+pub trait TEventSubscriber: Send + Sync {
+	fn handle(&self, outbox: &OutBox) -> impl std::future::Future<Output = Result<(), BaseError>> + Send;
+}
+
+/// Position in the event stream. Events after the position are read next.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamPosition {
+	pub transaction_id: i64,
+	/// Id of the last outbox read in the transaction, or `None` when every outbox of the transaction has been read.
+	pub outbox_id: Option<Id>,
+}
+
+impl PartialOrd for StreamPosition {
+	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for StreamPosition {
+	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+		// * `None` comes after any outbox of the transaction
+		self.transaction_id.cmp(&other.transaction_id).then_with(|| match (self.outbox_id, other.outbox_id) {
+			(Some(id), Some(other)) => id.cmp(&other),
+			(lhs, rhs) => lhs.is_none().cmp(&rhs.is_none()),
+		})
+	}
+}
+
+impl StreamPosition {
+	/// Beginning of the stream.
+	pub const START: Self = Self { transaction_id: 0, outbox_id: None };
+
+	/// End of the stream at the moment. Subscription starting from here only receives events committed afterwards.
+	pub async fn end(pool: &PgPool) -> Result<Self, BaseError> {
+		// * Transactions still in progress are not older than the oldest one in the snapshot, so they come after the position
+		let xmin: i64 = sqlx::query_scalar("SELECT pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT").fetch_one(pool).await?;
+		Ok(Self {
+			transaction_id: xmin - 1,
+			outbox_id: None,
+		})
+	}
+}
+
+pub struct EventSubscription {
+	pool: PgPool,
+	name: String,
+	channel: Option<String>,
+	start_from: StreamPosition,
+	poll_interval: Duration,
+	batch_size: i64,
+}
+
+impl EventSubscription {
+	/// Subscription is identified by name, under which its checkpoint is saved.
+	pub fn new(pool: PgPool, name: impl Into<String>) -> Self {
+		Self {
+			pool,
+			name: name.into(),
+			channel: None,
+			start_from: StreamPosition::START,
+			poll_interval: Duration::from_secs(5),
+			batch_size: 100,
+		}
+	}
+
+	/// Channel notified when outboxes are saved(see [ContextManager::with_outbox_channel]).
+	/// Without it, live mode relies on polling only.
+	///
+	/// [ContextManager::with_outbox_channel]: crate::prelude::ContextManager::with_outbox_channel
+	pub fn listen(mut self, channel: impl Into<String>) -> Self {
+		self.channel = Some(channel.into());
+		self
+	}
+
+	/// Position to start from when no checkpoint has been saved yet.
+	pub fn start_from(mut self, position: StreamPosition) -> Self {
+		self.start_from = position;
+		self
+	}
+
+	pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+		self.poll_interval = poll_interval;
+		self
+	}
+
+	pub fn batch_size(mut self, batch_size: i64) -> Self {
+		self.batch_size = batch_size;
+		self
+	}
+
+	/// Position saved last time, if any.
+	pub async fn checkpoint(&self) -> Result<Option<StreamPosition>, BaseError> {
+		let position: Option<(i64, Id)> = sqlx::query_as("SELECT transaction_id, outbox_id FROM service_subscription_checkpoint WHERE subscription = $1")
+			.bind(&self.name)
+			.fetch_optional(&self.pool)
+			.await?;
+		Ok(position.map(|(transaction_id, outbox_id)| StreamPosition {
+			transaction_id,
+			outbox_id: Some(outbox_id),
+		}))
+	}
+
+	/// Read events committed so far from the checkpoint, and return the number of events handled.
+	/// When subscriber fails, checkpoint is saved up to the last event handled and the error is returned.
+	pub async fn catch_up(&self, subscriber: &impl TEventSubscriber) -> Result<usize, BaseError> {
+		let mut position = self.checkpoint().await?.unwrap_or(self.start_from);
+		let mut handled = 0;
+		loop {
+			// * Id is bound as the type of `id` column, which depends on the id strategy in use
+			let after = match position.outbox_id {
+				Some(_) => "(transaction_id, id) > ($1, $3)",
+				None => "transaction_id > $1",
+			};
+			let query = format!(
+				r#"
+                SELECT id, aggregate_id, aggregate_name, topic, state, content_type, processed, create_dt, sequence, partition_key, transaction_id
+                FROM service_outbox
+                WHERE {after}
+                    AND transaction_id < pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT
+                ORDER BY transaction_id, id
+                LIMIT $2
+                "#
+			);
+			let mut query = sqlx::query(&query).bind(position.transaction_id).bind(self.batch_size);
+			if let Some(outbox_id) = position.outbox_id {
+				query = query.bind(outbox_id);
+			}
+			let rows = query.fetch_all(&self.pool).await?;
+
+			let mut res = Ok(());
+			let before = position;
+			for row in rows.iter() {
+				let outbox = OutBox::from_row(row)?;
+				if let Err(err) = subscriber.handle(&outbox).await {
+					res = Err(err);
+					break;
+				}
+				position = StreamPosition {
+					transaction_id: row.try_get("transaction_id")?,
+					outbox_id: Some(outbox.id),
+				};
+				handled += 1;
+			}
+			if position != before {
+				self.save_checkpoint(position).await?;
+			}
+			res?;
+
+			if (rows.len() as i64) < self.batch_size {
+				return Ok(handled);
+			}
+		}
+	}
+
+	/// Catch up, then keep handling events as they are committed until the future is dropped.
+	/// Failure of subscriber is logged and retried from the checkpoint on the next wake-up.
+	pub async fn run(&self, subscriber: &impl TEventSubscriber) -> Result<(), BaseError> {
+		// * Listen before reading so that events committed after the last read always wake the subscription up
+		let mut listener = match &self.channel {
+			Some(channel) => {
+				let mut listener = PgListener::connect_with(&self.pool).await?;
+				listener.listen(channel).await?;
+				Some(listener)
+			}
+			None => None,
+		};
+
+		loop {
+			if let Err(err) = self.catch_up(subscriber).await {
+				tracing::error!("failed to handle events of subscription {}! {:?}", self.name, err);
+			}
+
+			match listener.as_mut() {
+				Some(listener) => match tokio::time::timeout(self.poll_interval, listener.recv()).await {
+					Ok(Ok(_notification)) => (),
+					Ok(Err(err)) => {
+						tracing::warn!("failed to receive outbox notification! {}", err);
+						tokio::time::sleep(self.poll_interval).await;
+					}
+					Err(_elapsed) => (),
+				},
+				None => tokio::time::sleep(self.poll_interval).await,
+			}
+		}
+	}
+
+	async fn save_checkpoint(&self, position: StreamPosition) -> Result<(), BaseError> {
+		sqlx::query(
+			r#"
+            INSERT INTO service_subscription_checkpoint (subscription, transaction_id, outbox_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (subscription)
+            DO UPDATE SET transaction_id = EXCLUDED.transaction_id, outbox_id = EXCLUDED.outbox_id, update_dt = NOW()
+            "#,
+		)
+		.bind(&self.name)
+		.bind(position.transaction_id)
+		.bind(position.outbox_id)
+		.execute(&self.pool)
+		.await?;
+		Ok(())
+	}
+}
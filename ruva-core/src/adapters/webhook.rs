@@ -0,0 +1,261 @@
+//! ### Webhook publisher
+//! [WebhookPublisher] delivers outboxes to endpoints registered per topic, POSTing them as
+//! [CloudEvent](crate::prelude::CloudEvent) in structured JSON mode.
+//!
+//! `<timestamp>.<delivery id>.<body>` is signed with HMAC-SHA256 by the secret of the endpoint and the signature is sent
+//! in `X-Ruva-Signature` header as `sha256=<hex>`, along with the unix timestamp in `X-Ruva-Timestamp` and outbox id in
+//! `X-Ruva-Delivery`. Receivers verify the body and reject stale or replayed deliveries with them.
+//! Each endpoint is retried with its own [RetryPolicy].
+//!
+//! Result of delivery to each endpoint is recorded in `service_outbox_delivery`. Endpoints delivered to already are
+//! skipped when the outbox is published again after failure of the others.
+//!
+//! ```sql
+//! CREATE TABLE service_outbox_delivery (
+//!     outbox_id BIGINT NOT NULL, -- of the type of `service_outbox.id`
+//!     endpoint TEXT NOT NULL,
+//!     status TEXT NOT NULL,
+//!     attempts INT NOT NULL,
+//!     response_status INT,
+//!     last_error TEXT,
+//!     update_dt TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+//!     PRIMARY KEY (outbox_id, endpoint)
+//! );
+//! ```
+
+use crate::prelude::{BaseError, CloudEvent, Id, OutBox, RetryPolicy, SystemClock, TClock, TOutBoxPublisher};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const SIGNATURE_HEADER: &str = "X-Ruva-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Ruva-Timestamp";
+pub const DELIVERY_HEADER: &str = "X-Ruva-Delivery";
+
+#[derive(Debug, Clone)]
+pub struct WebhookEndpoint {
+	pub url: String,
+	secret: String,
+	pub retry_policy: RetryPolicy,
+	pub timeout: Duration,
+}
+
+impl WebhookEndpoint {
+	pub fn new(url: impl Into<String>, secret: impl Into<String>) -> Self {
+		Self {
+			url: url.into(),
+			secret: secret.into(),
+			retry_policy: RetryPolicy::new(3).backoff(Duration::from_millis(500)),
+			timeout: Duration::from_secs(10),
+		}
+	}
+
+	pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+		self.retry_policy = retry_policy;
+		self
+	}
+
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	/// Signature sent in [SIGNATURE_HEADER], covering timestamp and delivery id as well as body.
+	pub fn sign(&self, timestamp: i64, delivery_id: &str, body: &[u8]) -> String {
+		let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).expect("HMAC takes key of any size");
+		mac.update(format!("{}.{}.", timestamp, delivery_id).as_bytes());
+		mac.update(body);
+		let signature = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+		format!("sha256={}", signature)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+	Delivered,
+	Failed,
+}
+
+impl DeliveryStatus {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			DeliveryStatus::Delivered => "delivered",
+			DeliveryStatus::Failed => "failed",
+		}
+	}
+}
+
+struct Delivery {
+	status: DeliveryStatus,
+	attempts: i32,
+	response_status: Option<i32>,
+	last_error: Option<String>,
+}
+
+pub struct WebhookPublisher {
+	pool: PgPool,
+	client: reqwest::Client,
+	endpoints: HashMap<String, Vec<WebhookEndpoint>>,
+	clock: Arc<dyn TClock>,
+}
+
+impl WebhookPublisher {
+	/// Delivery status is recorded in the database of the given pool.
+	pub fn new(pool: PgPool) -> Self {
+		Self {
+			pool,
+			client: reqwest::Client::new(),
+			endpoints: HashMap::new(),
+			clock: Arc::new(SystemClock),
+		}
+	}
+
+	/// Read signing timestamp from the given clock in place of the system clock.
+	pub fn with_clock(mut self, clock: impl TClock + 'static) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	/// Register endpoint outboxes of the topic are delivered to.
+	pub fn endpoint(mut self, topic: impl Into<String>, endpoint: WebhookEndpoint) -> Self {
+		self.endpoints.entry(topic.into()).or_default().push(endpoint);
+		self
+	}
+
+	async fn deliver(&self, endpoint: &WebhookEndpoint, outbox_id: Id, body: &[u8]) -> Delivery {
+		let delivery_id = outbox_id.to_string();
+
+		let mut attempts: usize = 0;
+		loop {
+			attempts += 1;
+			// * Signed again on each attempt so that retries carry fresh timestamp
+			let timestamp = self.clock.now().timestamp();
+			let res = self
+				.client
+				.post(&endpoint.url)
+				.timeout(endpoint.timeout)
+				.header(reqwest::header::CONTENT_TYPE, "application/cloudevents+json")
+				.header(SIGNATURE_HEADER, endpoint.sign(timestamp, &delivery_id, body))
+				.header(TIMESTAMP_HEADER, timestamp.to_string())
+				.header(DELIVERY_HEADER, &delivery_id)
+				.body(body.to_vec())
+				.send()
+				.await;
+
+			let (response_status, last_error, retryable) = match res {
+				Ok(response) if response.status().is_success() => {
+					return Delivery {
+						status: DeliveryStatus::Delivered,
+						attempts: attempts as i32,
+						response_status: Some(response.status().as_u16() as i32),
+						last_error: None,
+					};
+				}
+				// * Client errors other than throttling won't be fixed by retrying
+				Ok(response) => {
+					let status = response.status();
+					(
+						Some(status.as_u16() as i32),
+						format!("Unexpected Response: {}", status),
+						status.is_server_error() || status.as_u16() == 429,
+					)
+				}
+				Err(err) => (None, err.to_string(), true),
+			};
+
+			if !retryable || attempts > endpoint.retry_policy.max_retries {
+				return Delivery {
+					status: DeliveryStatus::Failed,
+					attempts: attempts as i32,
+					response_status,
+					last_error: Some(last_error),
+				};
+			}
+			tracing::warn!(
+				"failed to deliver outbox {} to {}! {} Retrying({}/{})",
+				outbox_id,
+				endpoint.url,
+				last_error,
+				attempts,
+				endpoint.retry_policy.max_retries
+			);
+			tokio::time::sleep(endpoint.retry_policy.delay(attempts)).await;
+		}
+	}
+
+	async fn delivered_endpoints(&self, outbox_id: Id) -> Result<Vec<String>, BaseError> {
+		Ok(sqlx::query_scalar("SELECT endpoint FROM service_outbox_delivery WHERE outbox_id = $1 AND status = $2")
+			.bind(outbox_id)
+			.bind(DeliveryStatus::Delivered.as_str())
+			.fetch_all(&self.pool)
+			.await?)
+	}
+
+	async fn record(&self, outbox_id: Id, endpoint: &WebhookEndpoint, delivery: &Delivery) -> Result<(), BaseError> {
+		sqlx::query(
+			r#"
+            INSERT INTO service_outbox_delivery AS d
+                (outbox_id, endpoint, status, attempts, response_status, last_error)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (outbox_id, endpoint)
+            DO UPDATE SET
+                status = EXCLUDED.status,
+                attempts = d.attempts + EXCLUDED.attempts,
+                response_status = EXCLUDED.response_status,
+                last_error = EXCLUDED.last_error,
+                update_dt = NOW()
+            "#,
+		)
+		.bind(outbox_id)
+		.bind(&endpoint.url)
+		.bind(delivery.status.as_str())
+		.bind(delivery.attempts)
+		.bind(delivery.response_status)
+		.bind(&delivery.last_error)
+		.execute(&self.pool)
+		.await?;
+		Ok(())
+	}
+}
+
+impl TOutBoxPublisher for WebhookPublisher {
+	/// Deliver outbox to endpoints of its topic at the same time.
+	/// Fails with `BaseError::WebhookDeliveryFailed` of the first endpoint whose delivery failed.
+	async fn publish(&self, outbox: &OutBox) -> Result<(), BaseError> {
+		let Some(endpoints) = self.endpoints.get(&outbox.topic) else {
+			return Ok(());
+		};
+
+		let body = serde_json::to_vec(&CloudEvent::try_from(outbox)?).map_err(|err| BaseError::CodecError(err.to_string()))?;
+		let delivered = self.delivered_endpoints(outbox.id).await?;
+
+		let deliveries = futures::future::join_all(endpoints.iter().filter(|endpoint| !delivered.contains(&endpoint.url)).map(|endpoint| {
+			let body = &body;
+			async move {
+				let delivery = self.deliver(endpoint, outbox.id, body).await;
+				self.record(outbox.id, endpoint, &delivery).await?;
+				Ok::<_, BaseError>((endpoint, delivery))
+			}
+		}))
+		.await;
+
+		let mut failed = None;
+		for delivery in deliveries {
+			let (endpoint, delivery) = delivery?;
+			if delivery.status == DeliveryStatus::Failed {
+				tracing::error!("failed to deliver outbox {} to {}! {:?}", outbox.id, endpoint.url, delivery.last_error);
+				failed.get_or_insert(BaseError::WebhookDeliveryFailed {
+					endpoint: endpoint.url.clone(),
+					status: delivery.response_status.map(|status| status as u16),
+				});
+			}
+		}
+		match failed {
+			Some(err) => Err(err),
+			None => Ok(()),
+		}
+	}
+}
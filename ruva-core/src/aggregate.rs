@@ -1,8 +1,17 @@
 use std::collections::VecDeque;
 
-use crate::prelude::TEvent;
+use crate::prelude::{BaseError, OutBox, TEvent};
 
 pub trait TAggregate: Send + Sync + Default {
+	/// Name of aggregate, the same as the one recorded in outbox of its events.
+	fn aggregate_name() -> &'static str
+	where
+		Self: Sized,
+	{
+		let name = std::any::type_name::<Self>();
+		name.split('<').next().unwrap_or(name).rsplit("::").next().unwrap_or(name)
+	}
+
 	fn collect_events(&mut self) -> VecDeque<std::sync::Arc<dyn TEvent>> {
 		if !self.events().is_empty() {
 			self.take_events()
@@ -15,3 +24,84 @@ pub trait TAggregate: Send + Sync + Default {
 	fn take_events(&mut self) -> std::collections::VecDeque<std::sync::Arc<dyn TEvent>>;
 	fn raise_event(&mut self, event: std::sync::Arc<dyn TEvent>);
 }
+
+/// Aggregate with `{Name}Adapter`, implemented by `#[aggregate]`.
+pub trait TAdaptable: TAggregate {
+	type Adapter: From<Self> + Into<Self>
+	where
+		Self: Sized;
+}
+
+/// Aggregate rebuilt from its events, that is, outboxes of the aggregate in order of sequence.
+///
+/// Rebuilding can start from snapshot of `{Name}Adapter` instead of the first event.
+/// See [SnapshotStore](crate::prelude::SnapshotStore).
+pub trait TEventSourced: TAdaptable {
+	fn apply(&mut self, outbox: &OutBox) -> Result<(), BaseError>;
+
+	/// Number of events applied since the last snapshot at which new snapshot is taken.
+	fn snapshot_frequency() -> i64
+	where
+		Self: Sized,
+	{
+		100
+	}
+
+	/// Version of the shape of `{Name}Adapter`. Bump it when the adapter changes so that snapshots taken before are ignored.
+	fn schema_version() -> i32
+	where
+		Self: Sized,
+	{
+		1
+	}
+}
+
+/// Key of advisory lock taken on aggregate.
+///
+/// Identifier is hashed together with aggregate name whatever its type is,
+/// so that the same value locks the same key whether it is given as [SnowFlake](crate::prelude::SnowFlake) or `i64`
+/// while it doesn't collide with that of other aggregate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateLockKey(pub String);
+
+impl std::fmt::Display for AggregateLockKey {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+impl From<crate::prelude::SnowFlake> for AggregateLockKey {
+	fn from(value: crate::prelude::SnowFlake) -> Self {
+		Self(value.to_string())
+	}
+}
+
+impl From<i64> for AggregateLockKey {
+	fn from(value: i64) -> Self {
+		Self(value.to_string())
+	}
+}
+
+impl From<i32> for AggregateLockKey {
+	fn from(value: i32) -> Self {
+		Self(value.to_string())
+	}
+}
+
+impl From<String> for AggregateLockKey {
+	fn from(value: String) -> Self {
+		Self(value)
+	}
+}
+
+impl From<&str> for AggregateLockKey {
+	fn from(value: &str) -> Self {
+		Self(value.to_string())
+	}
+}
+
+impl From<uuid::Uuid> for AggregateLockKey {
+	fn from(value: uuid::Uuid) -> Self {
+		Self(value.to_string())
+	}
+}
@@ -1,21 +1,113 @@
-use super::executor::TConnection;
-use crate::{make_smart_pointer, prelude::TEvent};
+use super::executor::{TConnection, TenantConnections};
+use super::handler::TJoinedEventHandler;
+use crate::{
+	make_smart_pointer,
+	prelude::{BaseError, Id, JsonCodec, OutBox, SnowFlakeIdGenerator, SystemClock, TClock, TCodec, TEvent, TIdGenerator, TransactionOptions},
+};
 use std::{collections::VecDeque, sync::Arc};
 
 /// Request Context Manager
 /// it lives as long as the request lives
-
 pub struct ContextManager {
 	pub event_queue: VecDeque<Arc<dyn TEvent>>,
-	pub conn: &'static dyn TConnection,
+	pub conn: Arc<dyn TConnection>,
+
+	/// Tenant the request is made for. It is used to pick connection when [TenantConnections] is given.
+	pub tenant_id: Option<String>,
+
+	/// Channel notified on with `pg_notify` when outboxes are saved, to wake up outbox consumers.
+	pub outbox_channel: Option<String>,
+
+	/// Codec payload of events is encoded with in outbox, unless given to event itself.
+	pub codec: Option<Arc<dyn TCodec>>,
+
+	/// Clock the request tells time with, including creation time of outboxes.
+	pub clock: Arc<dyn TClock>,
+
+	/// Generator of ids the request uses, including ids of outboxes.
+	pub id_generator: Arc<dyn TIdGenerator>,
+
+	/// Handlers that run in the transaction of the command that raised the event, before it commits.
+	pub(crate) joined_event_handler: Option<&'static TJoinedEventHandler>,
 }
 
 pub type AtomicContextManager = Arc<ContextManager>;
 
 impl ContextManager {
 	/// Creation of context manager returns context manager AND event receiver
-	pub fn new(conn: &'static dyn TConnection) -> Self {
-		Self { event_queue: VecDeque::new(), conn }
+	pub fn new(conn: Arc<dyn TConnection>) -> Self {
+		Self {
+			event_queue: VecDeque::new(),
+			conn,
+			tenant_id: None,
+			outbox_channel: None,
+			codec: None,
+			clock: Arc::new(SystemClock),
+			id_generator: Arc::new(SnowFlakeIdGenerator),
+			joined_event_handler: None,
+		}
+	}
+
+	pub fn with_tenant_id(mut self, tenant_id: impl Into<String>) -> Self {
+		self.tenant_id = Some(tenant_id.into());
+		self
+	}
+
+	pub fn with_outbox_channel(mut self, channel: impl Into<String>) -> Self {
+		self.outbox_channel = Some(channel.into());
+		self
+	}
+
+	pub fn with_codec(mut self, codec: impl TCodec + 'static) -> Self {
+		self.codec = Some(Arc::new(codec));
+		self
+	}
+
+	pub fn with_clock(mut self, clock: impl TClock + 'static) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	pub fn with_id_generator(mut self, id_generator: impl TIdGenerator + 'static) -> Self {
+		self.id_generator = Arc::new(id_generator);
+		self
+	}
+
+	/// Outbox of the event with id and creation time of the request, encoded with the codec of the event or, if not given, of the request.
+	pub fn outbox(&self, event: &dyn TEvent) -> Result<OutBox, BaseError> {
+		let metadata = event.metadata();
+		let codec = event.codec().or(self.codec.as_deref()).unwrap_or(&JsonCodec);
+		Ok(OutBox::new(
+			metadata.aggregate_id,
+			metadata.aggregate_name,
+			metadata.topic,
+			event.encode_payload(codec)?,
+			self.clock.as_ref(),
+			self.id_generator.as_ref(),
+		)
+		.with_content_type(codec.content_type())
+		.with_partition_key(event.partition_key()))
+	}
+
+	/// Connection for the request.
+	/// If connections are given per tenant, the one for the tenant of the request is returned.
+	pub fn connection(&self) -> Result<Arc<dyn TConnection>, BaseError> {
+		let Some(connections) = self.conn.downcast_ref::<TenantConnections>() else {
+			return Ok(Arc::clone(&self.conn));
+		};
+		let Some(tenant_id) = self.tenant_id.as_deref() else {
+			tracing::error!("Tenant Id Must Be Given To Use Tenant Connections!");
+			return Err(BaseError::TenantNotGiven);
+		};
+		connections.get(tenant_id).ok_or_else(|| {
+			tracing::error!("Connection For Tenant {} Not Found!", tenant_id);
+			BaseError::UnknownTenant(tenant_id.to_string())
+		})
+	}
+
+	pub fn with_joined_event_handler(mut self, joined_event_handler: Option<&'static TJoinedEventHandler>) -> Self {
+		self.joined_event_handler = joined_event_handler;
+		self
 	}
 
 	/// SAFETY: This is safe because we are sure this method is used only in the context of command and event handling
@@ -31,9 +123,14 @@ make_smart_pointer!(ContextManager, VecDeque<Arc<dyn TEvent>>, event_queue);
 pub struct Context {
 	pub(crate) curr_events: VecDeque<std::sync::Arc<dyn TEvent>>,
 	pub(crate) super_ctx: AtomicContextManager,
+	pub(crate) transaction_options: TransactionOptions,
 
 	#[cfg(feature = "sqlx-postgres")]
 	pub(crate) pg_transaction: Option<sqlx::Transaction<'static, sqlx::Postgres>>,
+
+	/// Savepoints of nested units of work, each holding the number of events collected before it was set.
+	#[cfg(feature = "sqlx-postgres")]
+	pub(crate) savepoints: Vec<usize>,
 }
 
 impl Context {
@@ -41,11 +138,37 @@ impl Context {
 		Self {
 			curr_events: Default::default(),
 			super_ctx,
+			transaction_options: Default::default(),
 			#[cfg(feature = "sqlx-postgres")]
 			pg_transaction: None,
+			#[cfg(feature = "sqlx-postgres")]
+			savepoints: Vec::new(),
 		}
 	}
 
+	pub fn with_transaction_options(mut self, transaction_options: TransactionOptions) -> Self {
+		self.transaction_options = transaction_options;
+		self
+	}
+
+	pub fn transaction_options(&self) -> &TransactionOptions {
+		&self.transaction_options
+	}
+
+	pub fn context_manager(&self) -> AtomicContextManager {
+		Arc::clone(&self.super_ctx)
+	}
+
+	/// Current time by the clock of the request.
+	pub fn now(&self) -> chrono::DateTime<chrono::Utc> {
+		self.super_ctx.clock.now()
+	}
+
+	/// New id from the id generator of the request.
+	pub fn next_id(&self) -> Id {
+		self.super_ctx.id_generator.next_id()
+	}
+
 	pub fn event_hook(&mut self, aggregate: &mut impl crate::prelude::TAggregate) {
 		self.set_current_events(aggregate.take_events());
 	}
@@ -92,7 +215,7 @@ async fn test_context_managers() {
 		context_manager.get_mut().push_back(std::sync::Arc::new(CustomEvent(order)));
 	}
 
-	let context_manager = Arc::new(ContextManager::new(&CustomConnection));
+	let context_manager = Arc::new(ContextManager::new(Arc::new(CustomConnection)));
 
 	let count = 10000000;
 	let futures = (0..count).map(|order| add_event_to_queue(Arc::clone(&context_manager), order));
@@ -102,3 +225,24 @@ async fn test_context_managers() {
 	let events = context_manager.iter().map(|e| e.downcast_ref::<CustomEvent>().unwrap().0).collect::<Vec<_>>();
 	assert_eq!(events, (0..count).collect::<Vec<_>>());
 }
+
+#[test]
+fn test_connection_is_picked_by_tenant_id() {
+	struct CustomConnection(&'static str);
+	impl TConnection for CustomConnection {}
+
+	let connections = TenantConnections::default()
+		.with_tenant("tenant_a", Arc::new(CustomConnection("a")))
+		.with_tenant("tenant_b", Arc::new(CustomConnection("b")));
+	let connections: Arc<dyn TConnection> = Arc::new(connections);
+
+	let tenant_of = |context_manager: ContextManager| context_manager.connection().map(|conn| conn.downcast_ref::<CustomConnection>().unwrap().0);
+
+	assert_eq!(tenant_of(ContextManager::new(Arc::clone(&connections)).with_tenant_id("tenant_a")).unwrap(), "a");
+	assert_eq!(tenant_of(ContextManager::new(Arc::clone(&connections)).with_tenant_id("tenant_b")).unwrap(), "b");
+	assert!(tenant_of(ContextManager::new(Arc::clone(&connections)).with_tenant_id("tenant_c")).is_err());
+	assert!(tenant_of(ContextManager::new(Arc::clone(&connections))).is_err());
+
+	// Connection that is not per tenant is returned as it is regardless of tenant id
+	assert_eq!(tenant_of(ContextManager::new(Arc::new(CustomConnection("single"))).with_tenant_id("tenant_a")).unwrap(), "single");
+}
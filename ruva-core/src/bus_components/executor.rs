@@ -1,22 +1,58 @@
+use std::sync::{Arc, PoisonError, RwLock};
+
 use downcast_rs::{impl_downcast, Downcast};
 
 pub trait TConnection: Send + Sync + Downcast {}
 
-#[cfg(feature = "sqlx-postgres")]
-impl TConnection for &'static sqlx::postgres::PgPool {}
-#[cfg(feature = "sqlx-postgres")]
-impl TConnection for Box<&'static sqlx::postgres::PgPool> {}
-
+// Connection is held as `Arc<dyn TConnection>` so pool created at runtime(per test, per tenant and so on) can be passed around without being leaked.
 #[cfg(feature = "sqlx-postgres")]
 impl TConnection for sqlx::postgres::PgPool {}
-#[cfg(feature = "sqlx-postgres")]
-impl TConnection for Box<sqlx::postgres::PgPool> {}
-
-#[cfg(feature = "sqlx-postgres")]
-impl TConnection for &'static mut sqlx::PgConnection {}
-#[cfg(feature = "sqlx-postgres")]
-impl TConnection for Box<&'static mut sqlx::PgConnection> {}
 
 // Design TConnection so each different connection can be implemented and return itself
 
 impl_downcast!(TConnection);
+
+/// Connections for database-per-tenant setup.
+/// When it is given to `ContextManager`, the connection for the tenant id of the request is picked
+/// so that everything done in the request, including events and outboxes, lands on the tenant's database.
+/// ## Example
+/// ```rust,no_run
+/// # use ruva_core::prelude::*;
+/// # use std::sync::Arc;
+/// # async fn run<C: TCommand>(bus: impl TMessageBus<(), BaseError, C> + Sync, command: C, pool_a: impl TConnection, pool_b: impl TConnection) -> Result<(), BaseError> {
+/// let connections = TenantConnections::default()
+///     .with_tenant("tenant_a", Arc::new(pool_a))
+///     .with_tenant("tenant_b", Arc::new(pool_b));
+///
+/// let context_manager = ContextManager::new(Arc::new(connections)).with_tenant_id("tenant_a");
+/// let res = bus.execute_and_wait_with_context(command, context_manager).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct TenantConnections {
+	// * Lock is held only for single map operation, which leaves the map consistent even if a holder panicked, so poisoning is ignored
+	connections: RwLock<hashbrown::HashMap<String, Arc<dyn TConnection>>>,
+}
+
+impl TenantConnections {
+	pub fn with_tenant(self, tenant_id: impl Into<String>, conn: Arc<dyn TConnection>) -> Self {
+		self.insert(tenant_id, conn);
+		self
+	}
+
+	/// Register connection for tenant. As it takes `&self`, tenant can be onboarded while the connections are in use.
+	pub fn insert(&self, tenant_id: impl Into<String>, conn: Arc<dyn TConnection>) {
+		self.connections.write().unwrap_or_else(PoisonError::into_inner).insert(tenant_id.into(), conn);
+	}
+
+	pub fn remove(&self, tenant_id: &str) -> Option<Arc<dyn TConnection>> {
+		self.connections.write().unwrap_or_else(PoisonError::into_inner).remove(tenant_id)
+	}
+
+	pub fn get(&self, tenant_id: &str) -> Option<Arc<dyn TConnection>> {
+		self.connections.read().unwrap_or_else(PoisonError::into_inner).get(tenant_id).cloned()
+	}
+}
+
+impl TConnection for TenantConnections {}
@@ -16,8 +16,9 @@
 pub mod uow;
 use crate::{
 	message::TCommand,
-	prelude::{ApplicationError, ApplicationResponse, BaseError, TCommandService, TSetCurrentEvents, TUnitOfWork},
+	prelude::{ApplicationError, ApplicationResponse, AtomicContextManager, BaseError, Context, RetryPolicy, TCommandService, TSetCurrentEvents, TUnitOfWork, TransactionOptions},
 };
+use std::sync::Arc;
 pub use uow::*;
 
 pub struct CommandHandler<T>(pub T);
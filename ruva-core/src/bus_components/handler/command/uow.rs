@@ -31,9 +31,19 @@ where
 		dep.begin().await?;
 
 		let result = (D1::get_handler())(cmd, &mut dep).await;
+
+		// * Nested unit of work the handler left open must not take the place of the outermost one on commit
+		if let Err(err) = dep.rollback_nested().await {
+			dep.close().await;
+			return Err(err.into());
+		}
 		match result {
 			Ok(val) => {
-				dep.commit().await?;
+				// * Pre-commit event handlers run in the same transaction, so their failure must not leave it open.
+				if let Err(err) = dep.commit().await {
+					dep.close().await;
+					return Err(err.into());
+				}
 				dep.close().await;
 
 				Ok(val)
@@ -56,6 +66,57 @@ where
 	}
 }
 
+/// Command service that re-runs the wrapped one with fresh [Context] when its transaction fails on conflict
+/// with concurrent ones(`BaseError::TransactionConflict`), as many times as [RetryPolicy] allows.
+/// As command is handled more than once, it must be [Clone].
+pub struct RetryOnConflict<C, F> {
+	cmd: C,
+	context_manager: AtomicContextManager,
+	transaction_options: TransactionOptions,
+	retry_policy: RetryPolicy,
+	service: F,
+}
+
+impl<C, F> RetryOnConflict<C, F> {
+	pub fn new(cmd: C, context_manager: AtomicContextManager, transaction_options: TransactionOptions, retry_policy: RetryPolicy, service: F) -> Self {
+		Self {
+			cmd,
+			context_manager,
+			transaction_options,
+			retry_policy,
+			service,
+		}
+	}
+}
+
+impl<C, F, S, R, E> TCommandService<R, E> for RetryOnConflict<C, F>
+where
+	C: TCommand + Clone,
+	F: Fn(CommandHandler<(C, Context)>) -> S + Send + Sync,
+	S: TCommandService<R, E>,
+	R: Send,
+	E: std::convert::Into<BaseError> + Clone + Send,
+{
+	async fn execute(self) -> Result<R, E> {
+		// * Events queued by failed attempt must not be handled
+		let queued = self.context_manager.len();
+
+		let mut attempt = 0;
+		loop {
+			let context = Context::new(Arc::clone(&self.context_manager)).with_transaction_options(self.transaction_options.clone());
+			match (self.service)(CommandHandler((self.cmd.clone(), context))).execute().await {
+				Err(err) if attempt < self.retry_policy.max_retries && matches!(err.clone().into(), BaseError::TransactionConflict(_)) => {
+					attempt += 1;
+					tracing::warn!("Transaction conflict on {:?}. Retrying({}/{})", self.cmd, attempt, self.retry_policy.max_retries);
+					self.context_manager.get_mut().truncate(queued);
+					tokio::time::sleep(self.retry_policy.delay(attempt)).await;
+				}
+				result => return result,
+			}
+		}
+	}
+}
+
 #[macro_export]
 #[doc(hidden)]
 macro_rules! __register_uow_services_internal {
@@ -65,6 +126,8 @@ macro_rules! __register_uow_services_internal {
         $h:expr,
 
         $(
+            $(#[transaction($($option:tt)*)])?
+            $(#[retry($($retry:tt)*)])?
             $command:ty => $handler:expr
         ),*
     ) => {
@@ -84,13 +147,77 @@ macro_rules! __register_uow_services_internal {
                     context_manager: ruva::AtomicContextManager,
                     cmd: $command,
                 ) -> impl ::ruva::TCommandService<$response, $error> {
-                    $h(::ruva::CommandHandler((cmd, ::ruva::Context::new(context_manager))))
+                    ::ruva::__uow_command_service!($h, cmd, context_manager, [$($($option)*)?], [$($($retry)*)?])
                 }
             }
         )*
     };
 }
 
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __uow_command_service {
+    ($h:expr, $cmd:ident, $context_manager:ident, [$($option:tt)*], []) => {
+        $h(::ruva::CommandHandler((
+            $cmd,
+            ::ruva::Context::new($context_manager).with_transaction_options(::ruva::__transaction_options!($($option)*)),
+        )))
+    };
+    ($h:expr, $cmd:ident, $context_manager:ident, [$($option:tt)*], [$max_retries:expr $(, backoff = $backoff:expr)?]) => {
+        ::ruva::RetryOnConflict::new(
+            $cmd,
+            $context_manager,
+            ::ruva::__transaction_options!($($option)*),
+            ::ruva::RetryPolicy::new($max_retries)$(.backoff($backoff))?,
+            $h,
+        )
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __transaction_options {
+    () => {
+        ::ruva::TransactionOptions::default()
+    };
+    (@ $options:expr;) => {
+        $options
+    };
+    (@ $options:expr; isolation = $level:ident $(, $($rest:tt)*)?) => {
+        ::ruva::__transaction_options!(@ $options.isolation_level(::ruva::IsolationLevel::$level); $($($rest)*)?)
+    };
+    (@ $options:expr; read_only $(, $($rest:tt)*)?) => {
+        ::ruva::__transaction_options!(@ $options.read_only(); $($($rest)*)?)
+    };
+    (@ $options:expr; $($unknown:tt)*) => {
+        compile_error!(concat!("Unknown transaction option: ", stringify!($($unknown)*), ". Expected `isolation = ReadCommitted|RepeatableRead|Serializable` or `read_only`"))
+    };
+    ($($option:tt)+) => {
+        ::ruva::__transaction_options!(@ ::ruva::TransactionOptions::default(); $($option)+)
+    };
+}
+
+/// Register command handlers run in unit of work.
+///
+/// ### Transaction
+/// Each command is handled in its own transaction. Characteristics of the transaction can be given with
+/// `#[transaction(isolation = ReadCommitted|RepeatableRead|Serializable, read_only)]`.
+///
+/// With `#[retry(max_retries)]` or `#[retry(max_retries, backoff = Duration)]`, command whose transaction failed on
+/// serialization failure or deadlock is handled again with fresh [Context](crate::prelude::Context), waiting twice as long
+/// as the previous attempt. Such command must be `Clone`.
+///
+/// ```rust,no_run
+/// ruva::register_uow_services!(
+///     ServiceResponse,
+///     ServiceError,
+///     #[transaction(isolation = Serializable)]
+///     #[retry(3)]
+///     MakeOrder => make_order,
+///     #[transaction(read_only)]
+///     GetOrder => get_order
+/// );
+/// ```
 #[macro_export]
 macro_rules! register_uow_services {
     // Case with custom handler function
@@ -100,10 +227,12 @@ macro_rules! register_uow_services {
         $h:expr,
 
         $(
+            $(#[transaction($($option:tt)*)])?
+            $(#[retry($($retry:tt)*)])?
             $command:ty => $handler:expr
         ),*
     ) => {
-       	ruva::__register_uow_services_internal!($response, $error, $h, $($command => $handler),*);
+       	ruva::__register_uow_services_internal!($response, $error, $h, $($(#[transaction($($option)*)])? $(#[retry($($retry)*)])? $command => $handler),*);
     };
 
     // Default case
@@ -112,9 +241,11 @@ macro_rules! register_uow_services {
         $error:ty,
 
         $(
+            $(#[transaction($($option:tt)*)])?
+            $(#[retry($($retry:tt)*)])?
             $command:ty => $handler:expr
         ),*
     ) => {
-        ruva::__register_uow_services_internal!($response, $error, ::std::convert::identity, $($command => $handler),*);
+        ruva::__register_uow_services_internal!($response, $error, ::std::convert::identity, $($(#[transaction($($option)*)])? $(#[retry($($retry)*)])? $command => $handler),*);
     };
 }
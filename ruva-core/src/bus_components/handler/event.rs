@@ -1,4 +1,7 @@
-use crate::{bus_components::contexts::AtomicContextManager, prelude::TEvent};
+use crate::{
+	bus_components::contexts::{AtomicContextManager, Context},
+	prelude::{BaseError, TEvent},
+};
 
 use std::pin::Pin;
 
@@ -19,3 +22,21 @@ impl<E> EventHandlers<E> {
 		}
 	}
 }
+
+/// Future of handler that runs on the [Context] of the command that raised the event.
+/// As it shares the transaction with the command, its error is surfaced on commit as `BaseError::JoinedHandlerFailed`,
+/// from which the command recovers the error of the handler.
+pub type TransactionalFuture<'a> = Pin<Box<dyn futures::Future<Output = Result<(), BaseError>> + Send + 'a>>;
+
+pub type TransactionalHandler = Box<dyn for<'a> Fn(std::sync::Arc<dyn TEvent>, &'a mut Context) -> TransactionalFuture<'a> + Send + Sync>;
+
+/// Event handlers that join the transaction of the command, keyed by topic.
+pub type TJoinedEventHandler = hashbrown::HashMap<String, Vec<TransactionalHandler>>;
+
+/// Helper to box closure as [TransactionalHandler] so that its signature can be inferred with higher-ranked lifetime.
+pub fn transactional_handler<F>(handler: F) -> TransactionalHandler
+where
+	F: for<'a> Fn(std::sync::Arc<dyn TEvent>, &'a mut Context) -> TransactionalFuture<'a> + Send + Sync + 'static,
+{
+	Box::new(handler)
+}
@@ -20,11 +20,13 @@
 
 use super::contexts::*;
 use super::executor::TConnection;
-use super::handler::EventHandlers;
+use super::handler::{EventHandlers, TJoinedEventHandler};
 use crate::prelude::{TCommand, TEvent};
 use crate::responses::{self, ApplicationError, ApplicationResponse, BaseError};
 use async_recursion::async_recursion;
 use async_trait::async_trait;
+use futures::FutureExt;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
 
 /// Event handlers `TEventBus` work on
@@ -33,11 +35,61 @@ pub type TEventHandler<E> = hashbrown::HashMap<String, EventHandlers<E>>;
 #[async_trait]
 pub trait TEventBus<E> {
 	fn event_handler(&self) -> &'static TEventHandler<E>;
+
+	/// Event handlers that join the transaction of the command that raised the event.
+	fn joined_event_handler(&self) -> Option<&'static TJoinedEventHandler> {
+		None
+	}
+}
+
+/// Run handler future, converting panic raised while it is being polled into `BaseError::HandlerPanicked`
+/// so that one misbehaving handler doesn't take down the rest of the processing.
+pub(crate) async fn catch_handler_panic<T, E>(fut: impl std::future::Future<Output = Result<T, E>>) -> Result<T, E>
+where
+	E: std::convert::From<BaseError>,
+{
+	match AssertUnwindSafe(fut).catch_unwind().await {
+		Ok(res) => res,
+		Err(payload) => Err(BaseError::HandlerPanicked(panic_message(payload)).into()),
+	}
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+	if let Some(msg) = payload.downcast_ref::<&str>() {
+		msg.to_string()
+	} else if let Some(msg) = payload.downcast_ref::<String>() {
+		msg.clone()
+	} else {
+		"Unknown Panic Payload".to_string()
+	}
 }
 
 /// This function is used to handle event. It is called recursively until there is no event left in the queue.
+/// Panic of handler is logged like other failures; see [handle_event_reporting_panic] to get it back.
+pub(crate) async fn handle_event<E>(msg: Arc<dyn TEvent>, context_manager: AtomicContextManager, event_handler: &'static TEventHandler<E>) -> Result<AtomicContextManager, E>
+where
+	E: ApplicationError + std::convert::From<crate::responses::BaseError> + std::convert::From<E>,
+	crate::responses::BaseError: std::convert::From<E>,
+{
+	process_event(msg, context_manager, event_handler).await.map(|(context_manager, _)| context_manager)
+}
+
+/// Same as [handle_event], but fails with `BaseError::HandlerPanicked` of the first handler that panicked once every event is processed,
+/// for events processed in background whose panic no one would see otherwise.
+pub(crate) async fn handle_event_reporting_panic<E>(msg: Arc<dyn TEvent>, context_manager: AtomicContextManager, event_handler: &'static TEventHandler<E>) -> Result<AtomicContextManager, E>
+where
+	E: ApplicationError + std::convert::From<crate::responses::BaseError> + std::convert::From<E>,
+	crate::responses::BaseError: std::convert::From<E>,
+{
+	match process_event(msg, context_manager, event_handler).await? {
+		(_, Some(panicked)) => Err(BaseError::HandlerPanicked(panicked).into()),
+		(context_manager, None) => Ok(context_manager),
+	}
+}
+
+/// Process event and the ones queued after it, returning message of the first handler that panicked along the way.
 #[async_recursion]
-async fn handle_event<E>(msg: Arc<dyn TEvent>, context_manager: AtomicContextManager, event_handler: &'static TEventHandler<E>) -> Result<AtomicContextManager, E>
+async fn process_event<E>(msg: Arc<dyn TEvent>, context_manager: AtomicContextManager, event_handler: &'static TEventHandler<E>) -> Result<(AtomicContextManager, Option<String>), E>
 where
 	E: ApplicationError + std::convert::From<crate::responses::BaseError> + std::convert::From<E>,
 	crate::responses::BaseError: std::convert::From<E>,
@@ -53,10 +105,12 @@ where
 		BaseError::NotFound
 	})?;
 
+	let mut panicked = None;
 	match handlers {
 		EventHandlers::Sync(h) => {
 			for (i, handler) in h.iter().enumerate() {
-				if let Err(err) = handler(msg.clone(), Arc::clone(&context_manager)).await {
+				// * Panic is treated like any other error so the following handlers still get the event.
+				if let Err(err) = catch_handler_panic(async { handler(msg.clone(), Arc::clone(&context_manager)).await }).await {
 					// ! Safety:: BaseError Must Be Enforced To Be Accepted As Variant On ServiceError
 					match err.into() {
 						BaseError::StopSentinel => {
@@ -73,16 +127,25 @@ where
 						err => {
 							let error_msg = format!("Error Occurred While Handling Event In {i}th Event! Error:{:?}", err);
 							crate::backtrace_error!("{}", error_msg);
+							if let BaseError::HandlerPanicked(msg) = err {
+								panicked.get_or_insert(msg);
+							}
 						}
 					}
 				}
 			}
 		}
 		EventHandlers::Async(h) => {
-			let futures = h.iter().map(|handler| handler(msg.clone(), Arc::clone(&context_manager)));
-			if let Err(err) = futures::future::try_join_all(futures).await {
-				let error_msg = format!("Error Occurred While Handling Event! Error:{:?}", err);
-				crate::backtrace_error!("{}", error_msg);
+			// * Each handler is awaited to completion regardless of failure(or panic) of its siblings.
+			let futures = h.iter().map(|handler| catch_handler_panic(async { handler(msg.clone(), Arc::clone(&context_manager)).await }));
+			for (i, res) in futures::future::join_all(futures).await.into_iter().enumerate() {
+				if let Err(err) = res {
+					let error_msg = format!("Error Occurred While Handling Event In {i}th Event! Error:{:?}", err);
+					crate::backtrace_error!("{}", error_msg);
+					if let BaseError::HandlerPanicked(msg) = err.into() {
+						panicked.get_or_insert(msg);
+					}
+				}
 			}
 		}
 	}
@@ -91,12 +154,13 @@ where
 	let incoming_event = context_manager.get_mut().pop_front();
 
 	if let Some(event) = incoming_event {
-		if let Err(err) = handle_event(event, Arc::clone(&context_manager), event_handler).await {
+		match process_event(event, Arc::clone(&context_manager), event_handler).await {
+			Ok((_, following)) => panicked = panicked.or(following),
 			// ! Safety:: BaseError Must Be Enforced To Be Accepted As Variant On ServiceError
-			tracing::error!("{:?}", err);
+			Err(err) => tracing::error!("{:?}", err),
 		}
 	}
-	Ok(context_manager)
+	Ok((context_manager, panicked))
 }
 
 /// Interface for messagebus to work on
@@ -117,17 +181,26 @@ where
 	/// This method is used to handle command and return result.
 	/// ## Example
 	/// ```rust,no_run
-	/// let res = service.execute_and_wait(message).await?;
+	/// let res = service.execute_and_wait(message, Arc::new(pool.clone())).await?;
 	/// ```
+	async fn execute_and_wait(&self, message: C, conn: Arc<dyn TConnection>) -> Result<R, E> {
+		self.execute_and_wait_with_context(message, ContextManager::new(conn)).await
+	}
 
-	async fn execute_and_wait(&self, message: C, conn: &'static dyn TConnection) -> Result<R, E> {
+	/// Same as `execute_and_wait` but on the given request context, for example, one that carries tenant id.
+	/// ## Example
+	/// ```rust,no_run
+	/// let context_manager = ContextManager::new(Arc::new(tenant_connections)).with_tenant_id("tenant_a");
+	/// let res = service.execute_and_wait_with_context(message, context_manager).await?;
+	/// ```
+	async fn execute_and_wait_with_context(&self, message: C, context_manager: ContextManager) -> Result<R, E> {
 		#[cfg(feature = "tracing")]
 		{
 			tracing::info!("{}", std::any::type_name::<C>());
 		}
 
-		let context_manager = Arc::new(ContextManager::new(conn));
-		let res = self.command_handler(Arc::clone(&context_manager), message).execute().await?;
+		let context_manager = Arc::new(context_manager.with_joined_event_handler(self.joined_event_handler()));
+		let res = catch_handler_panic(async { self.command_handler(Arc::clone(&context_manager), message).execute().await }).await?;
 
 		// Trigger event handler
 		if !context_manager.event_queue.is_empty() {
@@ -140,25 +213,32 @@ where
 	/// This method is used to handle command and return result proxy which holds the result and join handler.
 	/// ## Example
 	/// ```rust,no_run
-	/// let res = service.execute_and_forget(message).await?;
+	/// let res = service.execute_and_forget(message, Arc::new(pool.clone())).await?;
 	/// let res = res.wait_until_event_processing_done().await?;
 	/// let res = res.result();
 	/// ```
-	async fn execute_and_forget(&self, message: C, conn: &'static dyn TConnection) -> Result<CommandResponseWithEventFutures<R, E>, E> {
+	/// Events are processed in background until the queue is empty even if handler panics, after which
+	/// `wait_until_event_processing_done` fails with `BaseError::HandlerPanicked` of the first one that did.
+	async fn execute_and_forget(&self, message: C, conn: Arc<dyn TConnection>) -> Result<CommandResponseWithEventFutures<R, E>, E> {
+		self.execute_and_forget_with_context(message, ContextManager::new(conn)).await
+	}
+
+	/// Same as `execute_and_forget` but on the given request context, for example, one that carries tenant id.
+	async fn execute_and_forget_with_context(&self, message: C, context_manager: ContextManager) -> Result<CommandResponseWithEventFutures<R, E>, E> {
 		#[cfg(feature = "tracing")]
 		{
 			tracing::info!("{}", std::any::type_name::<C>());
 		}
 
-		let context_manager = Arc::new(ContextManager::new(conn));
-		let res = self.command_handler(Arc::clone(&context_manager), message).execute().await?;
+		let context_manager = Arc::new(context_manager.with_joined_event_handler(self.joined_event_handler()));
+		let res = catch_handler_panic(async { self.command_handler(Arc::clone(&context_manager), message).execute().await }).await?;
 		let mut res = CommandResponseWithEventFutures { result: res, join_handler: None };
 
 		// Trigger event handler
 		if !context_manager.event_queue.is_empty() {
 			let event = context_manager.get_mut().pop_front().unwrap();
 
-			res.join_handler = Some(tokio::spawn(handle_event(event, context_manager, self.event_handler())));
+			res.join_handler = Some(tokio::spawn(handle_event_reporting_panic(event, context_manager, self.event_handler())));
 		}
 		Ok(res)
 	}
@@ -178,7 +258,10 @@ where
 		if let Some(join_handler) = self.join_handler.take() {
 			join_handler.await.map_err(|err| {
 				tracing::error!("{:?}", err);
-				BaseError::ServiceError
+				match err.try_into_panic() {
+					Ok(payload) => BaseError::HandlerPanicked(panic_message(payload)),
+					Err(_) => BaseError::ServiceError,
+				}
 			})??;
 		}
 		Ok(self)
@@ -201,7 +284,43 @@ where
 /// );
 /// ```
 ///
-
+/// ## Transaction
+/// By default, whether handler opens transaction is left to the dependency built by the factory.
+/// Alternatively, you can declare it per handler with `#[transaction(...)]`, in which case the handler takes [Context] as second argument:
+/// * `own` - handler runs in its own transaction which is committed on success and rolled back on failure.
+/// * `none` - handler runs without transaction. Internally notifiable events it raises are still propagated.
+/// * `join` - handler runs in the transaction of the command that raised the event, right before it commits.
+///   As the transaction is shared, failure of the handler rolls back the command, which returns the error of the handler.
+///
+/// When every handler for an event must be atomic with the command, mark the event with `#[pre_commit]` instead.
+/// It is shorthand for `#[transaction(join)]` on each of its handlers, so they run the same way as `join` handlers do.
+///
+/// ```rust,no_run
+/// init_event_handler!(
+///     YourServiceError,
+///     |ctx| YourEventHandler(ctx),
+///     #[pre_commit]
+///     YourEvent:[increase_counter, check_limit],
+/// );
+/// ```
+///
+/// ```rust,no_run
+/// init_event_handler!(
+///     YourServiceError,
+///     |ctx| YourEventHandler(ctx),
+///     YourEvent:[
+///         #[transaction(own)] handler1,
+///         #[transaction(join)] handler2
+///     ],
+/// );
+///
+/// impl YourEventHandler {
+///     async fn handler1(self, event: YourEvent, context: &mut Context) -> Result<(), YourServiceError> {
+///         // No `begin`/`commit` required
+///         Ok(())
+///     }
+/// }
+/// ```
 #[macro_export]
 macro_rules! init_event_handler {
     (
@@ -209,7 +328,7 @@ macro_rules! init_event_handler {
 		$event_handler :expr,
 			$(
 				$(#[$asynchrony:ident])?
-				$event:ty:[$($handler:ident $(=>($($injectable:ident $(( $($arg:ident),* ))? ),*))?),* $(,)? ]
+				$event:ty:[$($(#[transaction($mode:ident)])? $handler:ident $(=>($($injectable:ident $(( $($arg:ident),* ))? ),*))?),* $(,)? ]
 			),*
 			$(,)?
 
@@ -224,21 +343,9 @@ macro_rules! init_event_handler {
 				} else {
 					::ruva::EventHandlers::Sync(vec![])
 				};
-				handlers.extend(vec![
-					$(
-						Box::new(
-							|e: ::std::sync::Arc<dyn ::ruva::TEvent>, context_manager: ruva::AtomicContextManager | -> ::ruva::Future<$E> {
-								let event_handler = $event_handler(context_manager);
-								Box::pin(event_handler.$handler(
-									// * Convert event so event handler accepts not Arc<dyn TEvent> but `event_happend` type of message.
-									// Safety:: client should access this vector of handlers by providing the corresponding event name
-									// So, when it is followed, it logically doesn't make sense to cause an error.
-									e.downcast_ref::<$event>().expect("Not Convertible!").clone(),
-								))
-							}
-						),
-					)*
-				]);
+				let mut event_handlers: ::ruva::Handlers<$E> = vec![];
+				::ruva::__register_event_handler!(@handlers event_handlers, [$($asynchrony)?], $E, $event_handler, $event, [$([$($mode)?] $handler),*]);
+				handlers.extend(event_handlers);
                 _map.insert(
                     stringify!($event).into(),
 					handlers
@@ -248,14 +355,275 @@ macro_rules! init_event_handler {
 			}
 		);
 
+		pub(crate) static JOINED_EVENT_HANDLERS: std::sync::LazyLock<ruva::TJoinedEventHandler> = std::sync::LazyLock::new(
+			||{
+				let mut _map : ::ruva::TJoinedEventHandler = ::ruva::HandlerMapper::new();
+				$(
+				let mut joined_handlers: ::std::vec::Vec<::ruva::TransactionalHandler> = vec![];
+				::ruva::__register_event_handler!(@joined joined_handlers, [$($asynchrony)?], $E, $event_handler, $event, [$([$($mode)?] $handler),*]);
+				if !joined_handlers.is_empty() {
+					_map.insert(stringify!($event).into(), joined_handlers);
+				}
+				)*
+				_map
+			}
+		);
+
 		impl ruva::TEventBus<$E> for ::ruva::MessageBus{
 			fn event_handler(&self) -> &'static ruva::TEventHandler<$E>{
 				&EVENT_HANDLERS
 			}
+			fn joined_event_handler(&self) -> Option<&'static ruva::TJoinedEventHandler>{
+				Some(&JOINED_EVENT_HANDLERS)
+			}
 		}
 
 	};
 
 }
 
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __register_event_handler {
+	// * `#[pre_commit]` is sugar for `#[transaction(join)]` on every handler of the event.
+	(@$target:ident $handlers:ident, [pre_commit], $E:ty, $event_handler:expr, $event:ty, [$([] $handler:ident),*]) => {
+		::ruva::__register_event_handler!(@$target $handlers, [], $E, $event_handler, $event, [$([join] $handler),*]);
+	};
+	(@$target:ident $handlers:ident, [pre_commit], $E:ty, $event_handler:expr, $event:ty, [$([$($mode:ident)?] $handler:ident),*]) => {
+		compile_error!("`#[transaction(...)]` is not allowed on `#[pre_commit]` event as all of its handlers join the transaction of the command.");
+	};
+	(@$target:ident $handlers:ident, $group:tt, $E:ty, $event_handler:expr, $event:ty, [$([$($mode:ident)?] $handler:ident),*]) => {
+		$(
+			::ruva::__register_event_handler!(@$target $handlers, $group, $E, $event_handler, $event, $handler $(, $mode)?);
+		)*
+	};
+
+	// * Handlers that join the transaction are dispatched by `Context` before commit, not by messagebus.
+	(@handlers $handlers:ident, [$($asynchrony:ident)?], $E:ty, $event_handler:expr, $event:ty, $handler:ident, join) => {};
+	(@joined $joined:ident, [$($asynchrony:ident)?], $E:ty, $event_handler:expr, $event:ty, $handler:ident, join) => {
+		$joined.push(::ruva::transactional_handler(|e: ::std::sync::Arc<dyn ::ruva::TEvent>, context: &mut ::ruva::Context| {
+			let event_handler = $event_handler(context.context_manager());
+			Box::pin(async move {
+				event_handler
+					.$handler(e.downcast_ref::<$event>().expect("Not Convertible!").clone(), context)
+					.await
+					.map_err(|err: $E| ::ruva::BaseError::JoinedHandlerFailed(::ruva::HandlerError::new(err)))
+			})
+		}));
+	};
+	(@joined $joined:ident, [$($asynchrony:ident)?], $E:ty, $event_handler:expr, $event:ty, $handler:ident $(, $mode:ident)?) => {};
+
+	(@handlers $handlers:ident, [$($asynchrony:ident)?], $E:ty, $event_handler:expr, $event:ty, $handler:ident, own) => {
+		$handlers.push(Box::new(|e: ::std::sync::Arc<dyn ::ruva::TEvent>, context_manager: ruva::AtomicContextManager| -> ::ruva::Future<$E> {
+			let event_handler = $event_handler(::std::sync::Arc::clone(&context_manager));
+			Box::pin(async move {
+				let mut context = ::ruva::Context::new(context_manager);
+				::ruva::TUnitOfWork::begin(&mut context).await?;
+				let result = event_handler.$handler(e.downcast_ref::<$event>().expect("Not Convertible!").clone(), &mut context).await;
+				::ruva::TUnitOfWork::rollback_nested(&mut context).await?;
+				match result {
+					Ok(()) => {
+						::ruva::TUnitOfWork::commit(&mut context).await?;
+						Ok::<(), $E>(())
+					}
+					Err(err) => {
+						::ruva::TUnitOfWork::rollback(&mut context).await?;
+						Err(err)
+					}
+				}
+			})
+		}));
+	};
+	(@handlers $handlers:ident, [$($asynchrony:ident)?], $E:ty, $event_handler:expr, $event:ty, $handler:ident, none) => {
+		$handlers.push(Box::new(|e: ::std::sync::Arc<dyn ::ruva::TEvent>, context_manager: ruva::AtomicContextManager| -> ::ruva::Future<$E> {
+			let event_handler = $event_handler(::std::sync::Arc::clone(&context_manager));
+			Box::pin(async move {
+				let mut context = ::ruva::Context::new(context_manager);
+				event_handler.$handler(e.downcast_ref::<$event>().expect("Not Convertible!").clone(), &mut context).await?;
+				context.send_internally_notifiable_messages().await;
+				Ok::<(), $E>(())
+			})
+		}));
+	};
+	(@handlers $handlers:ident, [$($asynchrony:ident)?], $E:ty, $event_handler:expr, $event:ty, $handler:ident, $mode:ident) => {
+		compile_error!(concat!("Unknown transaction mode `", stringify!($mode), "`. Expected one of `own`, `none` and `join`."));
+	};
+	(@handlers $handlers:ident, [$($asynchrony:ident)?], $E:ty, $event_handler:expr, $event:ty, $handler:ident) => {
+		$handlers.push(Box::new(|e: ::std::sync::Arc<dyn ::ruva::TEvent>, context_manager: ruva::AtomicContextManager| -> ::ruva::Future<$E> {
+			let event_handler = $event_handler(context_manager);
+			Box::pin(event_handler.$handler(
+				// * Convert event so event handler accepts not Arc<dyn TEvent> but `event_happend` type of message.
+				// Safety:: client should access this vector of handlers by providing the corresponding event name
+				// So, when it is followed, it logically doesn't make sense to cause an error.
+				e.downcast_ref::<$event>().expect("Not Convertible!").clone(),
+			))
+		}));
+	};
+}
+
 pub struct MessageBus;
+
+#[tokio::test]
+async fn test_panic_in_event_handler_does_not_stop_processing() {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	struct CustomConnection;
+	impl TConnection for CustomConnection {}
+	struct PanicEvent;
+	impl TEvent for PanicEvent {
+		fn state(&self) -> String {
+			"state".to_string()
+		}
+	}
+	struct FollowingEvent;
+	impl TEvent for FollowingEvent {
+		fn state(&self) -> String {
+			"state".to_string()
+		}
+	}
+
+	static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+	async fn panicking_handler(context_manager: AtomicContextManager) -> Result<(), BaseError> {
+		context_manager.get_mut().push_back(Arc::new(FollowingEvent));
+		panic!("event handler panicked")
+	}
+	async fn counting_handler() -> Result<(), BaseError> {
+		HANDLED.fetch_add(1, Ordering::SeqCst);
+		Ok(())
+	}
+
+	let mut event_handler: TEventHandler<BaseError> = hashbrown::HashMap::new();
+	event_handler.insert(
+		"PanicEvent".into(),
+		EventHandlers::Sync(vec![
+			Box::new(|_: Arc<dyn TEvent>, context_manager: AtomicContextManager| -> crate::prelude::Future<BaseError> { Box::pin(panicking_handler(context_manager)) }),
+			Box::new(|_: Arc<dyn TEvent>, _: AtomicContextManager| -> crate::prelude::Future<BaseError> { Box::pin(counting_handler()) }),
+		]),
+	);
+	event_handler.insert(
+		"FollowingEvent".into(),
+		EventHandlers::Async(vec![
+			Box::new(|_: Arc<dyn TEvent>, _: AtomicContextManager| -> crate::prelude::Future<BaseError> { Box::pin(async { panic!("async event handler panicked") }) }),
+			Box::new(|_: Arc<dyn TEvent>, _: AtomicContextManager| -> crate::prelude::Future<BaseError> { Box::pin(counting_handler()) }),
+		]),
+	);
+	let event_handler: &'static TEventHandler<BaseError> = Box::leak(Box::new(event_handler));
+
+	let context_manager = Arc::new(ContextManager::new(Arc::new(CustomConnection)));
+	let res = handle_event(Arc::new(PanicEvent), context_manager, event_handler).await;
+
+	assert!(res.is_ok());
+	assert_eq!(HANDLED.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_panic_in_command_handler_returns_handler_panicked() {
+	struct CustomConnection;
+	impl TConnection for CustomConnection {}
+
+	#[derive(Debug)]
+	struct PanicCommand;
+	impl TCommand for PanicCommand {}
+
+	struct PanicService;
+	impl TCommandService<(), BaseError> for PanicService {
+		async fn execute(self) -> Result<(), BaseError> {
+			panic!("command handler panicked")
+		}
+	}
+
+	struct TestBus;
+	impl TEventBus<BaseError> for TestBus {
+		fn event_handler(&self) -> &'static TEventHandler<BaseError> {
+			Box::leak(Box::default())
+		}
+	}
+	impl TMessageBus<(), BaseError, PanicCommand> for TestBus {
+		fn command_handler(&self, _: AtomicContextManager, _: PanicCommand) -> impl TCommandService<(), BaseError> {
+			PanicService
+		}
+	}
+
+	let res = TestBus.execute_and_wait(PanicCommand, Arc::new(CustomConnection)).await;
+	assert!(matches!(res, Err(BaseError::HandlerPanicked(msg)) if msg == "command handler panicked"));
+}
+
+#[tokio::test]
+async fn test_panic_in_event_handler_in_background_is_returned_on_wait() {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	struct CustomConnection;
+	impl TConnection for CustomConnection {}
+	struct PanicEvent;
+	impl TEvent for PanicEvent {
+		fn state(&self) -> String {
+			"state".to_string()
+		}
+	}
+	struct FollowingEvent;
+	impl TEvent for FollowingEvent {
+		fn state(&self) -> String {
+			"state".to_string()
+		}
+	}
+
+	#[derive(Debug)]
+	struct RaiseCommand;
+	impl TCommand for RaiseCommand {}
+
+	struct RaiseService(AtomicContextManager);
+	impl TCommandService<(), BaseError> for RaiseService {
+		async fn execute(self) -> Result<(), BaseError> {
+			self.0.get_mut().push_back(Arc::new(PanicEvent));
+			Ok(())
+		}
+	}
+
+	static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+	struct TestBus;
+	impl TEventBus<BaseError> for TestBus {
+		fn event_handler(&self) -> &'static TEventHandler<BaseError> {
+			static EVENT_HANDLER: std::sync::OnceLock<TEventHandler<BaseError>> = std::sync::OnceLock::new();
+			EVENT_HANDLER.get_or_init(|| {
+				let mut event_handler: TEventHandler<BaseError> = hashbrown::HashMap::new();
+				event_handler.insert(
+					"PanicEvent".into(),
+					EventHandlers::Sync(vec![Box::new(|_: Arc<dyn TEvent>, context_manager: AtomicContextManager| -> crate::prelude::Future<BaseError> {
+						Box::pin(async move {
+							context_manager.get_mut().push_back(Arc::new(FollowingEvent));
+							panic!("background event handler panicked")
+						})
+					})]),
+				);
+				event_handler.insert(
+					"FollowingEvent".into(),
+					EventHandlers::Sync(vec![Box::new(|_: Arc<dyn TEvent>, _: AtomicContextManager| -> crate::prelude::Future<BaseError> {
+						Box::pin(async {
+							HANDLED.fetch_add(1, Ordering::SeqCst);
+							Ok(())
+						})
+					})]),
+				);
+				event_handler
+			})
+		}
+	}
+	impl TMessageBus<(), BaseError, RaiseCommand> for TestBus {
+		fn command_handler(&self, context_manager: AtomicContextManager, _: RaiseCommand) -> impl TCommandService<(), BaseError> {
+			RaiseService(context_manager)
+		}
+	}
+
+	// * Event queued after the panic is still processed, and the panic comes back once processing is done
+	let res = TestBus.execute_and_forget(RaiseCommand, Arc::new(CustomConnection)).await.unwrap();
+	let res = res.wait_until_event_processing_done().await;
+	assert!(matches!(res, Err(BaseError::HandlerPanicked(msg)) if msg == "background event handler panicked"));
+	assert_eq!(HANDLED.load(Ordering::SeqCst), 1);
+
+	// * Bus goes on with later commands
+	let res = TestBus.execute_and_forget(RaiseCommand, Arc::new(CustomConnection)).await.unwrap();
+	assert!(matches!(res.wait_until_event_processing_done().await, Err(BaseError::HandlerPanicked(_))));
+	assert_eq!(HANDLED.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,78 @@
+//! ### Clock and id generator
+//! Time and ids are taken from [TClock] and [TIdGenerator](crate::prelude::TIdGenerator) given to [ContextManager](crate::prelude::ContextManager),
+//! so that handlers and outboxes can be made reproducible in tests.
+//!
+//! [SystemClock] and [SnowFlakeIdGenerator](crate::prelude::SnowFlakeIdGenerator) are used unless others are given.
+//!
+//! ```rust,no_run
+//! let context_manager = ContextManager::new(conn)
+//!     .with_clock(FixedClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()))
+//!     .with_id_generator(SequentialIdGenerator::new(1));
+//!
+//! async fn handler(cmd: Command, context: &mut Context) -> Result<(), Error> {
+//!     let order = Order::new(context.next_id(), context.now());
+//!     ...
+//! }
+//! ```
+
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+
+pub trait TClock: Send + Sync {
+	fn now(&self) -> DateTime<Utc>;
+}
+
+// * So that clock can be moved by test while it is in use
+impl<T: TClock + ?Sized> TClock for std::sync::Arc<T> {
+	fn now(&self) -> DateTime<Utc> {
+		(**self).now()
+	}
+}
+
+/// Clock of the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl TClock for SystemClock {
+	fn now(&self) -> DateTime<Utc> {
+		Utc::now()
+	}
+}
+
+/// Clock that stays at the given time until it is moved.
+#[derive(Debug)]
+pub struct FixedClock(Mutex<DateTime<Utc>>);
+
+impl FixedClock {
+	pub fn new(now: DateTime<Utc>) -> Self {
+		Self(Mutex::new(now))
+	}
+
+	pub fn set(&self, now: DateTime<Utc>) {
+		*self.0.lock().unwrap() = now;
+	}
+
+	pub fn advance(&self, duration: chrono::Duration) {
+		*self.0.lock().unwrap() += duration;
+	}
+}
+
+impl TClock for FixedClock {
+	fn now(&self) -> DateTime<Utc> {
+		*self.0.lock().unwrap()
+	}
+}
+
+#[test]
+fn test_fixed_clock_moves_only_when_told() {
+	let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+	let clock = FixedClock::new(start);
+	assert_eq!(clock.now(), start);
+	assert_eq!(clock.now(), start);
+
+	clock.advance(chrono::Duration::seconds(5));
+	assert_eq!(clock.now(), start + chrono::Duration::seconds(5));
+
+	clock.set(start);
+	assert_eq!(clock.now(), start);
+}
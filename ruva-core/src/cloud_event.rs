@@ -0,0 +1,250 @@
+//! ### CloudEvents
+//! [CloudEvent] is [CloudEvents 1.0](https://github.com/cloudevents/spec) envelope in structured JSON mode.
+//! Outbox maps to it as follows:
+//!
+//! | CloudEvents       | OutBox                     |
+//! |-------------------|----------------------------|
+//! | `id`              | `id`                       |
+//! | `source`          | `aggregate_name`           |
+//! | `subject`         | `aggregate_id`             |
+//! | `type`            | `topic`                    |
+//! | `time`            | `create_dt`                |
+//! | `datacontenttype` | `content_type`             |
+//! | `data`            | `state` of JSON payload    |
+//! | `data_base64`     | `state` of others          |
+//! | `sequence`        | `sequence`(extension)      |
+//! | `partitionkey`    | `partition_key`(extension) |
+//!
+//! Other extension attributes, such as correlation id, are kept in `extensions`.
+//!
+//! ```rust,no_run
+//! let event = CloudEvent::try_from(&outbox)?.with_correlation_id("request-1")?;
+//! let json = serde_json::to_string(&event)?;
+//! ```
+
+use crate::prelude::{BaseError, EventMetadata, Id, JsonCodec, OutBox, TCodec};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const SPEC_VERSION: &str = "1.0";
+pub const CORRELATION_ID: &str = "correlationid";
+pub const CAUSATION_ID: &str = "causationid";
+const SEQUENCE: &str = "sequence";
+const PARTITION_KEY: &str = "partitionkey";
+// * Attributes defined by the spec, which extension attribute would duplicate in the envelope
+const RESERVED_ATTRIBUTES: [&str; 9] = ["specversion", "id", "source", "type", "subject", "time", "datacontenttype", "dataschema", "data"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudEvent {
+	pub specversion: String,
+	pub id: String,
+	pub source: String,
+	#[serde(rename = "type")]
+	pub ty: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub subject: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub time: Option<DateTime<Utc>>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub datacontenttype: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub data: Option<Value>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub data_base64: Option<String>,
+	/// Extension attributes, serialized as top-level attributes of the envelope.
+	#[serde(flatten)]
+	pub extensions: BTreeMap<String, Value>,
+}
+
+impl CloudEvent {
+	/// Add extension attribute. As required by the spec, name must consist of lower-case alphabets and digits
+	/// and must not be the one of attributes the spec defines.
+	pub fn with_extension(mut self, name: &str, value: impl Into<Value>) -> Result<Self, BaseError> {
+		if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()) {
+			return Err(BaseError::CodecError(format!("Invalid Extension Attribute Name: {}", name)));
+		}
+		if RESERVED_ATTRIBUTES.contains(&name) {
+			return Err(BaseError::CodecError(format!("Reserved Attribute Name: {}", name)));
+		}
+		self.extensions.insert(name.to_string(), value.into());
+		Ok(self)
+	}
+
+	pub fn extension(&self, name: &str) -> Option<&Value> {
+		self.extensions.get(name)
+	}
+
+	pub fn with_correlation_id(self, correlation_id: impl Into<String>) -> Result<Self, BaseError> {
+		self.with_extension(CORRELATION_ID, correlation_id.into())
+	}
+
+	pub fn correlation_id(&self) -> Option<&str> {
+		self.extension(CORRELATION_ID).and_then(Value::as_str)
+	}
+
+	pub fn with_causation_id(self, causation_id: impl Into<String>) -> Result<Self, BaseError> {
+		self.with_extension(CAUSATION_ID, causation_id.into())
+	}
+
+	pub fn causation_id(&self) -> Option<&str> {
+		self.extension(CAUSATION_ID).and_then(Value::as_str)
+	}
+
+	pub fn metadata(&self) -> EventMetadata {
+		EventMetadata {
+			aggregate_id: self.subject.clone().unwrap_or_default(),
+			aggregate_name: self.source.clone(),
+			topic: self.ty.clone(),
+		}
+	}
+
+	/// Payload as bytes encoded in `datacontenttype`.
+	pub fn data_bytes(&self) -> Result<Vec<u8>, BaseError> {
+		match (&self.data, &self.data_base64) {
+			(_, Some(data_base64)) => STANDARD.decode(data_base64).map_err(|err| BaseError::CodecError(err.to_string())),
+			(Some(data), None) => JsonCodec.encode(data),
+			(None, None) => Ok(vec![]),
+		}
+	}
+}
+
+impl TryFrom<&OutBox> for CloudEvent {
+	type Error = BaseError;
+
+	fn try_from(outbox: &OutBox) -> Result<Self, Self::Error> {
+		// * JSON payload is embedded as it is, others are carried in base64
+		let (data, data_base64) = if outbox.content_type == JsonCodec.content_type() {
+			(Some(JsonCodec.decode(&outbox.state)?), None)
+		} else {
+			(None, Some(STANDARD.encode(&outbox.state)))
+		};
+
+		let mut extensions = BTreeMap::new();
+		extensions.insert(SEQUENCE.to_string(), Value::String(outbox.sequence.to_string()));
+		extensions.insert(PARTITION_KEY.to_string(), Value::String(outbox.partition_key.clone()));
+
+		Ok(Self {
+			specversion: SPEC_VERSION.to_string(),
+			id: outbox.id.to_string(),
+			source: outbox.aggregate_name.clone(),
+			ty: outbox.topic.clone(),
+			subject: Some(outbox.aggregate_id.clone()),
+			time: Some(outbox.create_dt),
+			datacontenttype: Some(outbox.content_type.clone()),
+			data,
+			data_base64,
+			extensions,
+		})
+	}
+}
+
+impl TryFrom<CloudEvent> for OutBox {
+	type Error = BaseError;
+
+	fn try_from(event: CloudEvent) -> Result<Self, Self::Error> {
+		if event.specversion != SPEC_VERSION {
+			return Err(BaseError::CodecError(format!("Unsupported Spec Version: {}", event.specversion)));
+		}
+		let id = event.id.parse::<Id>()?;
+		let state = event.data_bytes()?;
+		let aggregate_id = event.subject.clone().unwrap_or_default();
+
+		// * Extensions may come as either string or number
+		let extension = |name: &str| {
+			event.extension(name).map(|value| match value {
+				Value::String(value) => value.clone(),
+				value => value.to_string(),
+			})
+		};
+		let sequence = extension(SEQUENCE).and_then(|sequence| sequence.parse().ok()).unwrap_or_default();
+		let partition_key = extension(PARTITION_KEY).unwrap_or_else(|| aggregate_id.clone());
+
+		Ok(OutBox {
+			id,
+			aggregate_id,
+			aggregate_name: event.source,
+			topic: event.ty,
+			state,
+			content_type: event.datacontenttype.unwrap_or_else(|| JsonCodec.content_type().to_string()),
+			processed: false,
+			create_dt: event.time.unwrap_or_default(),
+			sequence,
+			partition_key,
+		})
+	}
+}
+
+#[test]
+fn test_outbox_to_cloud_event_and_back() {
+	let outbox = OutBox::new(
+		"1".into(),
+		"Order".into(),
+		"OrderPlaced".into(),
+		r#"{"id":1}"#.into(),
+		&crate::prelude::SystemClock,
+		&crate::prelude::SnowFlakeIdGenerator,
+	)
+	.with_partition_key("customer-1");
+
+	let event = CloudEvent::try_from(&outbox).unwrap().with_correlation_id("request-1").unwrap();
+	let json = serde_json::to_value(&event).unwrap();
+	assert_eq!(json["specversion"], "1.0");
+	assert_eq!(json["id"], outbox.id.to_string());
+	assert_eq!(json["source"], "Order");
+	assert_eq!(json["subject"], "1");
+	assert_eq!(json["type"], "OrderPlaced");
+	assert_eq!(json["datacontenttype"], "application/json");
+	assert_eq!(json["data"], serde_json::json!({ "id": 1 }));
+	assert_eq!(json["partitionkey"], "customer-1");
+	assert_eq!(json["correlationid"], "request-1");
+	assert!(json.get("data_base64").is_none());
+
+	let event: CloudEvent = serde_json::from_value(json).unwrap();
+	assert_eq!(event.correlation_id(), Some("request-1"));
+	assert_eq!(event.metadata().aggregate_name, "Order");
+	let restored = OutBox::try_from(event).unwrap();
+	assert_eq!(restored.id, outbox.id);
+	assert_eq!(restored.state, outbox.state);
+	assert_eq!(restored.partition_key, "customer-1");
+}
+
+#[test]
+fn test_binary_payload_is_carried_in_base64() {
+	let outbox = OutBox::new(
+		"1".into(),
+		"Order".into(),
+		"OrderPlaced".into(),
+		r#"{"id":1}"#.into(),
+		&crate::prelude::SystemClock,
+		&crate::prelude::SnowFlakeIdGenerator,
+	)
+	.encode_with(&crate::prelude::MessagePackCodec)
+	.unwrap();
+
+	let event = CloudEvent::try_from(&outbox).unwrap();
+	assert!(event.data.is_none());
+	assert_eq!(event.datacontenttype.as_deref(), Some("application/msgpack"));
+
+	let restored = OutBox::try_from(event).unwrap();
+	assert_eq!(restored.state, outbox.state);
+	assert_eq!(restored.value().unwrap(), serde_json::json!({ "id": 1 }));
+}
+
+#[test]
+fn test_invalid_extension_name_is_rejected() {
+	let outbox = OutBox::new(
+		"1".into(),
+		"Order".into(),
+		"OrderPlaced".into(),
+		"{}".into(),
+		&crate::prelude::SystemClock,
+		&crate::prelude::SnowFlakeIdGenerator,
+	);
+	assert!(CloudEvent::try_from(&outbox).unwrap().with_extension("Correlation-Id", "1").is_err());
+	for name in ["id", "source", "type", "data", "specversion"] {
+		assert!(CloudEvent::try_from(&outbox).unwrap().with_extension(name, "1").is_err(), "{}", name);
+	}
+}
@@ -0,0 +1,133 @@
+//! ### Codec
+//! [TCodec] encodes payload of event into bytes stored in outbox, along with its content type.
+//! Outbox is decoded with the codec matching the stored content type, see [codec_for].
+//! Codecs other than the built-in ones must be registered with [register_codec] to be decoded.
+//!
+//! Codec is chosen in the following order:
+//! - Codec given to event with `#[codec(...)]`
+//! - Codec given to [ContextManager](crate::prelude::ContextManager) with `with_codec`
+//! - [JsonCodec]
+//!
+//! ```rust,no_run
+//! #[derive(Serialize, Deserialize, Clone, TEvent)]
+//! #[externally_notifiable(OrderAggregate)]
+//! #[codec(ruva::MessagePackCodec)]
+//! pub struct OrderPlaced {
+//!     #[identifier]
+//!     pub id: i64,
+//! }
+//! ```
+
+use crate::prelude::BaseError;
+use serde_json::Value;
+use std::sync::{PoisonError, RwLock};
+
+pub trait TCodec: Send + Sync {
+	fn content_type(&self) -> &'static str;
+	/// Encode value as it is, without going through JSON.
+	fn encode(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, BaseError>;
+	fn decode(&self, bytes: &[u8]) -> Result<Value, BaseError>;
+}
+
+pub struct JsonCodec;
+impl TCodec for JsonCodec {
+	fn content_type(&self) -> &'static str {
+		"application/json"
+	}
+	fn encode(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, BaseError> {
+		serde_json::to_vec(value).map_err(codec_error)
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<Value, BaseError> {
+		serde_json::from_slice(bytes).map_err(codec_error)
+	}
+}
+
+pub struct MessagePackCodec;
+impl TCodec for MessagePackCodec {
+	fn content_type(&self) -> &'static str {
+		"application/msgpack"
+	}
+	fn encode(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, BaseError> {
+		// * Encoded as map so that field names are kept
+		rmp_serde::to_vec_named(value).map_err(codec_error)
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<Value, BaseError> {
+		rmp_serde::from_slice(bytes).map_err(codec_error)
+	}
+}
+
+pub struct CborCodec;
+impl TCodec for CborCodec {
+	fn content_type(&self) -> &'static str {
+		"application/cbor"
+	}
+	fn encode(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, BaseError> {
+		let mut bytes = vec![];
+		ciborium::into_writer(value, &mut bytes).map_err(codec_error)?;
+		Ok(bytes)
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<Value, BaseError> {
+		ciborium::from_reader(bytes).map_err(codec_error)
+	}
+}
+
+// * Registered once at startup and looked up for every outbox decoded, so codecs are leaked to be handed out as `'static`
+static CODECS: RwLock<Vec<&'static dyn TCodec>> = RwLock::new(Vec::new());
+
+/// Register codec so that outboxes of its content type can be decoded.
+/// Codec registered later takes precedence over the one of the same content type, including built-in ones.
+pub fn register_codec(codec: impl TCodec + 'static) {
+	let codec: &'static dyn TCodec = Box::leak(Box::new(codec));
+	let mut codecs = CODECS.write().unwrap_or_else(PoisonError::into_inner);
+	codecs.retain(|registered| registered.content_type() != codec.content_type());
+	codecs.push(codec);
+}
+
+/// Codec for the given content type, looked up in registered codecs and then built-in ones.
+pub fn codec_for(content_type: &str) -> Option<&'static dyn TCodec> {
+	let registered = CODECS.read().unwrap_or_else(PoisonError::into_inner).iter().find(|codec| codec.content_type() == content_type).copied();
+	registered.or(match content_type {
+		"application/json" => Some(&JsonCodec),
+		"application/msgpack" => Some(&MessagePackCodec),
+		"application/cbor" => Some(&CborCodec),
+		_ => None,
+	})
+}
+
+fn codec_error(err: impl std::fmt::Display) -> BaseError {
+	tracing::error!("failed to encode or decode payload! {}", err);
+	BaseError::CodecError(err.to_string())
+}
+
+#[test]
+fn test_codecs_round_trip() {
+	let value = serde_json::json!({ "id": 1, "name": "migo", "items": [1, 2], "nested": { "flag": true } });
+
+	for codec in [&JsonCodec as &dyn TCodec, &MessagePackCodec, &CborCodec] {
+		let bytes = codec.encode(&value).unwrap();
+		let decoded = codec_for(codec.content_type()).unwrap().decode(&bytes).unwrap();
+		assert_eq!(decoded, value, "{}", codec.content_type());
+	}
+	assert!(codec_for("text/plain").is_none());
+}
+
+#[test]
+fn test_registered_codec_is_looked_up() {
+	struct TestCodec;
+	impl TCodec for TestCodec {
+		fn content_type(&self) -> &'static str {
+			"application/x-ruva-test"
+		}
+		fn encode(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, BaseError> {
+			JsonCodec.encode(value)
+		}
+		fn decode(&self, bytes: &[u8]) -> Result<Value, BaseError> {
+			JsonCodec.decode(bytes)
+		}
+	}
+
+	assert!(codec_for("application/x-ruva-test").is_none());
+	register_codec(TestCodec);
+	let codec = codec_for("application/x-ruva-test").unwrap();
+	assert_eq!(codec.decode(&codec.encode(&serde_json::json!({ "id": 1 })).unwrap()).unwrap(), serde_json::json!({ "id": 1 }));
+}
@@ -0,0 +1,237 @@
+//! ### Id strategy
+//! [Id] is identifier of outboxes, and of aggregates that take it from [Context::next_id](crate::prelude::Context::next_id),
+//! in the format [TIdGenerator] of [ContextManager](crate::prelude::ContextManager) generates:
+//!
+//! * [SnowFlakeIdGenerator] - [SnowFlake], stored as `BIGINT`. Used unless other one is given.
+//! * [UuidV7IdGenerator] - UUIDv7, stored as `UUID`.
+//! * [UlidIdGenerator] - ULID, stored as `TEXT` in its canonical form.
+//!
+//! All of them are ordered by the time they are generated at. [Id] is serialized as number if it is integer and as string otherwise,
+//! and is bound to and read from database as the type above, so `id` column of `service_outbox` can be of the type of the strategy in use.
+//!
+//! Aggregate whose id field is of [Id] is created with its id taken from the generator by `with_next_id`, which `#[aggregate]` generates.
+//!
+//! ```rust,no_run
+//! let context_manager = ContextManager::new(conn).with_id_generator(UuidV7IdGenerator);
+//! ```
+
+use crate::prelude::{BaseError, SnowFlake};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use ulid::Ulid;
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Id {
+	/// Integer id such as [SnowFlake].
+	Int(i64),
+	Uuid(Uuid),
+	Ulid(Ulid),
+}
+
+impl Id {
+	pub fn as_i64(&self) -> Option<i64> {
+		match self {
+			Self::Int(id) => Some(*id),
+			_ => None,
+		}
+	}
+
+	pub fn as_uuid(&self) -> Option<Uuid> {
+		match self {
+			Self::Uuid(id) => Some(*id),
+			_ => None,
+		}
+	}
+
+	pub fn as_ulid(&self) -> Option<Ulid> {
+		match self {
+			Self::Ulid(id) => Some(*id),
+			_ => None,
+		}
+	}
+}
+
+impl Default for Id {
+	fn default() -> Self {
+		Self::Int(0)
+	}
+}
+
+impl From<i64> for Id {
+	fn from(value: i64) -> Self {
+		Self::Int(value)
+	}
+}
+
+impl From<SnowFlake> for Id {
+	fn from(value: SnowFlake) -> Self {
+		Self::Int(*value)
+	}
+}
+
+impl From<Uuid> for Id {
+	fn from(value: Uuid) -> Self {
+		Self::Uuid(value)
+	}
+}
+
+impl From<Ulid> for Id {
+	fn from(value: Ulid) -> Self {
+		Self::Ulid(value)
+	}
+}
+
+impl std::fmt::Display for Id {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Int(id) => write!(f, "{}", id),
+			Self::Uuid(id) => write!(f, "{}", id),
+			Self::Ulid(id) => write!(f, "{}", id),
+		}
+	}
+}
+
+/// Id is told by its form: integer, hyphenated UUID or 26 characters of ULID.
+impl FromStr for Id {
+	type Err = BaseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Ok(id) = s.parse::<i64>() {
+			return Ok(Self::Int(id));
+		}
+		match s.len() {
+			36 => Uuid::parse_str(s).map(Self::Uuid).map_err(|err| BaseError::CodecError(format!("Invalid Id {}: {}", s, err))),
+			_ => Ulid::from_string(s).map(Self::Ulid).map_err(|err| BaseError::CodecError(format!("Invalid Id {}: {}", s, err))),
+		}
+	}
+}
+
+impl Serialize for Id {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		// * Integer id stays number, as ids were before other strategies came in
+		match self {
+			Self::Int(id) => serializer.serialize_i64(*id),
+			_ => serializer.collect_str(self),
+		}
+	}
+}
+
+// * Integer ids written as string are accepted as well
+impl<'de> Deserialize<'de> for Id {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		struct IdVisitor;
+
+		impl Visitor<'_> for IdVisitor {
+			type Value = Id;
+
+			fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+				f.write_str("Id as a number or string")
+			}
+
+			fn visit_i64<E: de::Error>(self, id: i64) -> Result<Self::Value, E> {
+				Ok(Id::Int(id))
+			}
+
+			fn visit_u64<E: de::Error>(self, id: u64) -> Result<Self::Value, E> {
+				i64::try_from(id).map(Id::Int).map_err(|_| E::custom(format!("Id out of range: {}", id)))
+			}
+
+			fn visit_str<E: de::Error>(self, id: &str) -> Result<Self::Value, E> {
+				id.parse().map_err(|err| E::custom(format!("{:?}", err)))
+			}
+		}
+
+		deserializer.deserialize_any(IdVisitor)
+	}
+}
+
+pub trait TIdGenerator: Send + Sync {
+	fn next_id(&self) -> Id;
+}
+
+/// Generator of [SnowFlake] ids.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SnowFlakeIdGenerator;
+
+impl TIdGenerator for SnowFlakeIdGenerator {
+	fn next_id(&self) -> Id {
+		SnowFlake::generate().into()
+	}
+}
+
+/// Generator of UUIDv7, whose leading 48 bits are milliseconds since UNIX epoch.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidV7IdGenerator;
+
+impl TIdGenerator for UuidV7IdGenerator {
+	fn next_id(&self) -> Id {
+		Uuid::now_v7().into()
+	}
+}
+
+/// Generator of ULID.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UlidIdGenerator;
+
+impl TIdGenerator for UlidIdGenerator {
+	fn next_id(&self) -> Id {
+		Ulid::new().into()
+	}
+}
+
+/// Generator of integer ids increasing by one from the given one.
+#[derive(Debug)]
+pub struct SequentialIdGenerator(AtomicI64);
+
+impl SequentialIdGenerator {
+	pub fn new(start: i64) -> Self {
+		Self(AtomicI64::new(start))
+	}
+}
+
+impl TIdGenerator for SequentialIdGenerator {
+	fn next_id(&self) -> Id {
+		self.0.fetch_add(1, Ordering::SeqCst).into()
+	}
+}
+
+#[test]
+fn test_sequential_ids() {
+	let ids = SequentialIdGenerator::new(10);
+	assert_eq!((0..3).map(|_| ids.next_id()).collect::<Vec<_>>(), vec![Id::Int(10), Id::Int(11), Id::Int(12)]);
+}
+
+#[test]
+fn test_id_round_trips_through_string_and_serde() {
+	for id in [SnowFlakeIdGenerator.next_id(), UuidV7IdGenerator.next_id(), UlidIdGenerator.next_id()] {
+		assert_eq!(id.to_string().parse::<Id>().unwrap(), id);
+		let json = serde_json::to_value(id).unwrap();
+		match id {
+			Id::Int(int) => assert_eq!(json, serde_json::json!(int)),
+			_ => assert_eq!(json, serde_json::Value::String(id.to_string())),
+		}
+		assert_eq!(serde_json::from_value::<Id>(json).unwrap(), id);
+	}
+	assert_eq!(serde_json::from_value::<Id>(serde_json::json!("42")).unwrap(), Id::Int(42));
+	assert!("not-an-id".parse::<Id>().is_err());
+}
+
+#[test]
+fn test_ids_are_ordered_by_generation() {
+	for generator in [&UuidV7IdGenerator as &dyn TIdGenerator, &UlidIdGenerator, &SnowFlakeIdGenerator] {
+		let first = generator.next_id();
+		std::thread::sleep(std::time::Duration::from_millis(2));
+		let second = generator.next_id();
+		assert!(first < second);
+		assert!(first.to_string() < second.to_string() || first.as_i64().is_some());
+	}
+}
@@ -2,11 +2,17 @@ mod adapters;
 mod aggregate;
 mod backtrace;
 mod bus_components;
+mod clock;
+mod cloud_event;
+mod codec;
+mod id;
 mod macros;
 mod message;
 mod outbox;
 mod responses;
 mod snowflake;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod unit_of_work;
 
 pub mod prelude {
@@ -16,15 +22,32 @@ pub mod prelude {
 	pub use crate::bus_components::contexts::ContextManager;
 	pub use crate::bus_components::contexts::TSetCurrentEvents;
 	pub use crate::bus_components::executor::TConnection;
+	pub use crate::bus_components::executor::TenantConnections;
 	pub use crate::bus_components::handler::*;
 	pub use crate::bus_components::messagebus::*;
+	pub use crate::clock::{FixedClock, SystemClock, TClock};
+	pub use crate::cloud_event::{CloudEvent, CAUSATION_ID, CORRELATION_ID};
+	pub use crate::codec::{codec_for, register_codec, CborCodec, JsonCodec, MessagePackCodec, TCodec};
 
+	#[cfg(feature = "sqlx-postgres")]
+	pub use crate::adapters::sqlx::node::{MachineIdAllocator, MachineIdLease};
+	#[cfg(feature = "sqlx-postgres")]
+	pub use crate::adapters::sqlx::outbox::{ArchivedOutBox, OutBoxArchive, OutBoxConsumer, OutBoxRetention, RetentionReport};
+	#[cfg(feature = "sqlx-postgres")]
+	pub use crate::adapters::sqlx::snapshot::{SnapshotStore, Versioned};
+	#[cfg(feature = "sqlx-postgres")]
+	pub use crate::adapters::sqlx::subscription::{EventSubscription, StreamPosition, TEventSubscriber};
+	#[cfg(feature = "webhook")]
+	pub use crate::adapters::webhook::{DeliveryStatus, WebhookEndpoint, WebhookPublisher, DELIVERY_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+	pub use crate::id::{Id, SequentialIdGenerator, SnowFlakeIdGenerator, TIdGenerator, UlidIdGenerator, UuidV7IdGenerator};
 	pub use crate::message::*;
-	pub use crate::outbox::OutBox;
-	pub use crate::responses::{ApplicationError, ApplicationResponse, BaseError};
-	pub use crate::snowflake::SnowFlake;
+	pub use crate::outbox::{OutBox, TOutBoxPublisher};
+	pub use crate::responses::{ApplicationError, ApplicationResponse, BaseError, HandlerError};
+	pub use crate::snowflake::{ClockRegression, NodeId, NumericalUniqueIdGenerator, SnowFlake};
 	pub use crate::unit_of_work::*;
 	pub use async_trait::async_trait;
+	pub use chrono;
+	pub use erased_serde;
 	pub use hashbrown::HashMap as HandlerMapper;
 	pub use serde;
 	pub use serde::{Deserialize, Serialize};
@@ -33,6 +56,8 @@ pub mod prelude {
 	pub use sqlx;
 	pub use tokio;
 	pub use tracing;
+	pub use ulid;
+	pub use uuid;
 }
 
 pub mod event_macros {
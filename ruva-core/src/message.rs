@@ -15,7 +15,7 @@
 //! Here, `internally_notifiable` indicates that the event will be handled internally by `MessageBus`
 //! And the `externally_notifiable` means that the event will be stored in the form of `OutBox` and
 //! will be handled in the separate process (or thread)
-use crate::prelude::OutBox;
+use crate::prelude::{BaseError, JsonCodec, TCodec};
 use downcast_rs::{impl_downcast, Downcast};
 use std::fmt::Debug;
 
@@ -35,9 +35,29 @@ pub trait TEvent: Sync + Send + Downcast {
 			topic: event_name.to_string(),
 		}
 	}
-	fn outbox(&self) -> OutBox {
-		let metadata = self.metadata();
-		OutBox::new(metadata.aggregate_id, metadata.aggregate_name, metadata.topic, self.state())
+	/// The event itself to be encoded by codec. `TEvent` derive gives it.
+	/// Without it, payload is encoded from [TEvent::state], going through JSON.
+	fn payload(&self) -> Option<&dyn erased_serde::Serialize> {
+		None
+	}
+
+	/// Payload encoded with the given codec.
+	fn encode_payload(&self, codec: &dyn TCodec) -> Result<Vec<u8>, BaseError> {
+		match self.payload() {
+			Some(payload) => codec.encode(payload),
+			None if codec.content_type() == JsonCodec.content_type() => Ok(self.state().into_bytes()),
+			None => codec.encode(&serde_json::from_str::<serde_json::Value>(&self.state()).map_err(|err| BaseError::CodecError(err.to_string()))?),
+		}
+	}
+
+	/// Codec payload of the event is encoded with in outbox. If not given, the one of the bus is used.
+	fn codec(&self) -> Option<&'static dyn TCodec> {
+		None
+	}
+
+	/// Key of partition outboxes are published in order within. Aggregate id by default.
+	fn partition_key(&self) -> String {
+		self.metadata().aggregate_id
 	}
 
 	fn state(&self) -> String;
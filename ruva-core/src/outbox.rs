@@ -1,28 +1,93 @@
 use chrono::{DateTime, Utc};
 
-use crate::prelude::SnowFlake;
+use crate::prelude::{codec_for, BaseError, Id, JsonCodec, TClock, TCodec, TIdGenerator};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutBox {
-	pub id: i64,
+	pub id: Id,
 	pub aggregate_id: String,
 	pub aggregate_name: String,
 	pub topic: String,
-	pub state: String,
+	/// Payload of event encoded by codec of `content_type`.
+	pub state: Vec<u8>,
+	#[serde(default = "default_content_type")]
+	pub content_type: String,
 	pub processed: bool,
 	pub create_dt: DateTime<Utc>,
+
+	/// Sequence of outbox among the ones of the same aggregate, starting from 1. It is assigned when saved.
+	#[serde(default)]
+	pub sequence: i64,
+	#[serde(default)]
+	pub partition_key: String,
 }
 
 impl OutBox {
-	pub fn new(aggregate_id: String, aggregate_name: String, topic: String, state: String) -> Self {
+	/// Outbox of JSON payload, created at the time of the given clock with id of the given generator.
+	/// Payload of other content type is given along with `with_content_type`.
+	///
+	/// Outbox of event is made by [ContextManager::outbox](crate::prelude::ContextManager::outbox) with the clock and id generator of the request.
+	pub fn new(aggregate_id: String, aggregate_name: String, topic: String, state: Vec<u8>, clock: &dyn TClock, id_generator: &dyn TIdGenerator) -> Self {
 		Self {
-			id: *SnowFlake::generate(),
+			id: id_generator.next_id(),
+			partition_key: aggregate_id.clone(),
 			aggregate_id,
 			aggregate_name,
 			topic,
 			state,
+			content_type: JsonCodec.content_type().to_string(),
 			processed: false,
-			create_dt: Default::default(),
+			create_dt: clock.now(),
+			sequence: 0,
 		}
 	}
+
+	/// Re-encode payload with the given codec.
+	pub fn encode_with(mut self, codec: &dyn TCodec) -> Result<Self, BaseError> {
+		if codec.content_type() != self.content_type {
+			self.state = codec.encode(&self.value()?)?;
+			self.content_type = codec.content_type().to_string();
+		}
+		Ok(self)
+	}
+
+	/// Payload decoded with the codec of its content type.
+	pub fn value(&self) -> Result<serde_json::Value, BaseError> {
+		let codec = codec_for(&self.content_type).ok_or_else(|| BaseError::CodecError(format!("Unknown Content Type: {}", self.content_type)))?;
+		codec.decode(&self.state)
+	}
+
+	pub fn decode<T: DeserializeOwned>(&self) -> Result<T, BaseError> {
+		serde_json::from_value(self.value()?).map_err(|err| BaseError::CodecError(err.to_string()))
+	}
+
+	pub fn with_id(mut self, id: impl Into<Id>) -> Self {
+		self.id = id.into();
+		self
+	}
+
+	pub fn with_create_dt(mut self, create_dt: DateTime<Utc>) -> Self {
+		self.create_dt = create_dt;
+		self
+	}
+
+	pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+		self.content_type = content_type.into();
+		self
+	}
+
+	pub fn with_partition_key(mut self, partition_key: impl Into<String>) -> Self {
+		self.partition_key = partition_key.into();
+		self
+	}
+}
+
+fn default_content_type() -> String {
+	JsonCodec.content_type().to_string()
+}
+
+/// Destination outboxes are relayed to, such as message broker.
+pub trait TOutBoxPublisher: Send + Sync {
+	fn publish(&self, outbox: &OutBox) -> impl std::future::Future<Output = Result<(), BaseError>> + Send;
 }
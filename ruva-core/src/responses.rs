@@ -8,6 +8,73 @@ pub enum BaseError {
 	StopSentinelWithEvent(std::sync::Arc<dyn TEvent>),
 	DatabaseError(String),
 	ServiceError,
+	/// Handler panicked while being processed. Carries the panic message.
+	HandlerPanicked(String),
+	/// Transaction failed on serialization failure or deadlock with concurrent ones and may succeed when retried.
+	TransactionConflict(String),
+	/// Payload couldn't be encoded or decoded.
+	CodecError(String),
+	/// Setting given is not valid, such as duration out of range or malformed table name.
+	InvalidConfiguration(String),
+	/// Outboxes couldn't be written to archive, for example on failure of file IO.
+	ArchiveError(String),
+	/// Lock on aggregate is held by other transaction.
+	AggregateLocked {
+		aggregate_name: String,
+		aggregate_id: String,
+	},
+	/// Failure injected in place of handler by simulation. Carries the handler and the seed.
+	InjectedFailure(String),
+	/// Node identity of id generator is out of bounds or conflicts with the one in use.
+	InvalidNodeId(String),
+	/// Clock moved backwards past the last id generated.
+	ClockRegressed(String),
+	/// Connections are given per tenant but the request doesn't carry tenant id.
+	TenantNotGiven,
+	/// No connection is registered for the tenant of the request.
+	UnknownTenant(String),
+	/// Handler that joined the transaction of the command failed. Carries the error it returned.
+	JoinedHandlerFailed(HandlerError),
+	/// Webhook endpoint didn't accept delivery. Status is the one it last responded with, if it responded at all.
+	WebhookDeliveryFailed {
+		endpoint: String,
+		status: Option<u16>,
+	},
+}
+
+/// Error of application handler returned, type-erased so that it can be carried by [BaseError].
+/// It is taken back by the conversion from [BaseError] into the error of the application, which `ApplicationError` derives.
+#[derive(Clone)]
+pub struct HandlerError {
+	error: std::sync::Arc<std::sync::Mutex<Option<Box<dyn std::any::Any + Send>>>>,
+	debug: std::sync::Arc<str>,
+}
+
+impl HandlerError {
+	pub fn new<E: ApplicationError>(error: E) -> Self {
+		Self {
+			debug: format!("{:?}", error).into(),
+			error: std::sync::Arc::new(std::sync::Mutex::new(Some(Box::new(error)))),
+		}
+	}
+
+	/// Take the error out if it is of the given type. It is taken only once, clones included.
+	pub fn take<E: ApplicationError>(&self) -> Option<E> {
+		let mut error = self.error.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		match error.take()?.downcast::<E>() {
+			Ok(taken) => Some(*taken),
+			Err(other) => {
+				*error = Some(other);
+				None
+			}
+		}
+	}
+}
+
+impl std::fmt::Debug for HandlerError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.debug)
+	}
 }
 
 pub trait ApplicationResponse: Send + Sync {}
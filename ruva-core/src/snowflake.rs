@@ -1,32 +1,112 @@
 #![allow(dead_code)]
 //! This is to generate global identifier
 
-use std::hint::spin_loop;
 use std::ops::Deref;
-use std::sync::atomic::{AtomicI16, AtomicI64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::de::Visitor;
 use serde::{de, Serialize, Serializer};
 
-#[derive(Debug)]
+use crate::prelude::{BaseError, TClock};
+use chrono::{DateTime, Utc};
+
+/// Identity of the node ids are generated on. Both ids take 5 bits, so they must be in `0..32`.
+/// Nodes generating ids at the same time must not share identity, or they can generate the same id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+	pub datacenter_id: i32,
+	pub machine_id: i32,
+}
+
+impl NodeId {
+	pub const MAX_ID: i32 = (1 << 5) - 1;
+
+	pub fn new(datacenter_id: i32, machine_id: i32) -> Result<Self, BaseError> {
+		for (name, id) in [("datacenter_id", datacenter_id), ("machine_id", machine_id)] {
+			if !(0..=Self::MAX_ID).contains(&id) {
+				return Err(BaseError::InvalidNodeId(format!("{} must be between 0 and {}, got {}", name, Self::MAX_ID, id)));
+			}
+		}
+		Ok(Self { datacenter_id, machine_id })
+	}
+
+	/// Read node identity from `DATACENTER_ID` and `MACHINE_ID`, each of which is `1` when not set.
+	pub fn from_env() -> Result<Self, BaseError> {
+		Self::parse(std::env::var("DATACENTER_ID").ok().as_deref(), std::env::var("MACHINE_ID").ok().as_deref())
+	}
+
+	fn parse(datacenter_id: Option<&str>, machine_id: Option<&str>) -> Result<Self, BaseError> {
+		let parse = |name: &str, id: Option<&str>| {
+			id.unwrap_or("1")
+				.trim()
+				.parse::<i32>()
+				.map_err(|err| BaseError::InvalidNodeId(format!("{} is not a number: {}", name, err)))
+		};
+		Self::new(parse("DATACENTER_ID", datacenter_id)?, parse("MACHINE_ID", machine_id)?)
+	}
+}
+
+impl std::fmt::Display for NodeId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}-{}", self.datacenter_id, self.machine_id)
+	}
+}
+
+const SEQUENCE_BITS: u32 = 12;
+const SEQUENCE_MASK: i64 = (1 << SEQUENCE_BITS) - 1;
+const NODE_MASK: i64 = (1 << 5) - 1;
+const MACHINE_SHIFT: u32 = SEQUENCE_BITS;
+const DATACENTER_SHIFT: u32 = MACHINE_SHIFT + 5;
+const TIMESTAMP_SHIFT: u32 = DATACENTER_SHIFT + 5;
+
+/// What [NumericalUniqueIdGenerator] does when the clock is found behind the last timestamp ids were generated at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ClockRegression {
+	/// Keep generating ids at the last timestamp until the clock catches up, waiting for it only when sequence of the timestamp runs out.
+	#[default]
+	Wait,
+	/// Fail with [BaseError::ClockRegressed].
+	Fail,
+}
+
 pub struct NumericalUniqueIdGenerator {
 	/// epoch used by the snowflake algorithm.
 	epoch: SystemTime,
 
 	/// datacenter_id and machine_id are fixed once the system is up running.
 	/// Any changes in datacenter IDs require careful review since an accidental change in those values can lead to ID conflicts
-	/// make sure that none of them is bigger than 5bits
+	/// Both are in `0..32` as they take 5 bits each; see [NodeId].
 	pub datacenter_id: i32,
 	pub machine_id: i32,
 
-	/// Most important 41 bits make up the timestamp section. As timestamps grow with time, IDs are sortable by time.
-	/// Maximum timestamp that can be represented in 41 bits is 2^41 -1 = 2199023255551 give is around 69 years.
-	timestamp: AtomicI64,
+	/// Timestamp of the last id in the upper bits and its sequence in the lower 12 bits, updated together with compare-and-swap
+	/// so that concurrent callers never take the same pair.
+	/// Timestamp takes 41 bits of id, which is around 69 years from the epoch. Sequence is 0 unless more than one ID is generated in a millisecond.
+	state: AtomicI64,
+
+	on_clock_regression: ClockRegression,
 
-	/// Sequence number is 12 bits, give gives us 2^12 combinations. This field is 0 unless more than one ID is generated in a millisecond on the same server
-	sequence_num: AtomicI16,
+	/// Clock to read time from in place of the system clock.
+	clock: Option<Arc<dyn TClock>>,
+
+	/// Set once the node may be held by other instance, after which no id is generated.
+	revoked: AtomicBool,
+}
+
+impl std::fmt::Debug for NumericalUniqueIdGenerator {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("NumericalUniqueIdGenerator")
+			.field("epoch", &self.epoch)
+			.field("datacenter_id", &self.datacenter_id)
+			.field("machine_id", &self.machine_id)
+			.field("state", &self.state)
+			.field("on_clock_regression", &self.on_clock_regression)
+			.field("revoked", &self.revoked)
+			.finish()
+	}
 }
 
 #[derive(Debug)]
@@ -64,16 +144,59 @@ impl NumericalUniqueIdGenerator {
 	/// let discord_epoch = UNIX_EPOCH + Duration::from_millis(1420070400000);
 	/// let id_generator = NumericalUniqueIdGenerator::with_epoch(1, 1, discord_epoch);
 	/// ```
+	///
+	/// # Panics
+	/// When `datacenter_id` or `machine_id` doesn't fit in 5 bits, as it would corrupt other parts of ids.
 	pub fn with_epoch(datacenter_id: i32, machine_id: i32, epoch: SystemTime) -> NumericalUniqueIdGenerator {
-		//TODO:limit the maximum of input args datacenter_id and machine_id
-		let timestamp = current_time_in_milli(epoch);
+		let NodeId { datacenter_id, machine_id } = NodeId::new(datacenter_id, machine_id).unwrap_or_else(|err| panic!("{:?}", err));
 
 		NumericalUniqueIdGenerator {
 			epoch,
-			timestamp: AtomicI64::new(timestamp),
 			datacenter_id,
 			machine_id,
-			sequence_num: AtomicI16::new(0),
+			state: AtomicI64::new(0),
+			on_clock_regression: ClockRegression::default(),
+			clock: None,
+			revoked: AtomicBool::new(false),
+		}
+	}
+
+	pub fn with_node(node: NodeId) -> NumericalUniqueIdGenerator {
+		Self::new(node.datacenter_id, node.machine_id)
+	}
+
+	pub fn on_clock_regression(mut self, on_clock_regression: ClockRegression) -> Self {
+		self.on_clock_regression = on_clock_regression;
+		self
+	}
+
+	/// Read time from the given clock in place of the system clock.
+	pub fn with_clock(mut self, clock: impl TClock + 'static) -> Self {
+		self.clock = Some(Arc::new(clock));
+		self
+	}
+
+	pub fn node(&self) -> NodeId {
+		NodeId {
+			datacenter_id: self.datacenter_id,
+			machine_id: self.machine_id,
+		}
+	}
+
+	/// Stop generating ids as the node may have been given to other instance, for example when its lease is lost.
+	/// It can't be undone; ids are generated again only with a generator of a node held anew.
+	pub fn revoke(&self) {
+		self.revoked.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_revoked(&self) -> bool {
+		self.revoked.load(Ordering::Relaxed)
+	}
+
+	fn now_millis(&self) -> i64 {
+		match &self.clock {
+			Some(clock) => clock.now().timestamp_millis() - DateTime::<Utc>::from(self.epoch).timestamp_millis(),
+			None => current_time_in_milli(self.epoch),
 		}
 	}
 
@@ -82,45 +205,62 @@ impl NumericalUniqueIdGenerator {
 	/// datacenter id takes 5 bits in the second place so left shift 17
 	/// machine id takes 5 bits in the third place so left shift 12
 	/// sequence number comes last.
-	fn get_snowflake(&self) -> i64 {
-		self.timestamp.load(Ordering::Relaxed) << 22 | ((self.datacenter_id << 17) as i64) | ((self.machine_id << 12) as i64) | (self.sequence_num.load(Ordering::Relaxed) as i64)
+	fn get_snowflake(&self, state: i64) -> i64 {
+		(state >> SEQUENCE_BITS) << TIMESTAMP_SHIFT | ((self.datacenter_id as i64) << DATACENTER_SHIFT) | ((self.machine_id as i64) << MACHINE_SHIFT) | (state & SEQUENCE_MASK)
 	}
 
-	/// The basic guarantee time punctuality.
+	/// Generate id, waiting for the next millisecond when 4096 ids have been generated in the current one.
 	///
-	/// Basic guarantee time punctuality.
-
-	/// When traffic peaks, 4096 in a millsec is simply not enough.
-	/// But setting time after every 4096 calls.
+	/// # Panics
+	/// When the clock moved backwards and [ClockRegression::Fail] is set, or the node is revoked.
+	/// Use [NumericalUniqueIdGenerator::try_generate] to handle them.
 	///
 	/// # Examples
 	///
 	/// ```
 	/// use snowflake::NumericalUniqueIdGenerator;
 	///
-	/// let mut id_generator = NumericalUniqueIdGenerator::new(1, 1);
+	/// let id_generator = NumericalUniqueIdGenerator::new(1, 1);
 	/// id_generator.generate();
 	/// ```
 	pub fn generate(&self) -> i64 {
-		self.sequence_num.store((self.sequence_num.load(Ordering::Relaxed) + 1) % 4096, Ordering::Relaxed);
-
-		let mut now_millis = current_time_in_milli(self.epoch);
-
-		// If the following is true, then check if sequence has been created 4092 times,
-		// and then busy wait until the next millisecond
-		// to prevent 'clock is moving backwards' situation.
-		if self.timestamp.load(Ordering::Relaxed) == now_millis {
-			// Maintenance `timestamp` for every 4096 ids generated.
-			if self.sequence_num.load(Ordering::Relaxed) == 0 {
-				now_millis = race_next_milli(self.timestamp.load(Ordering::Relaxed), self.epoch);
-				self.timestamp.store(now_millis, Ordering::Relaxed);
-			}
-		} else {
-			self.timestamp.store(now_millis, Ordering::Relaxed);
-			self.sequence_num.store(0, Ordering::Relaxed);
+		self.try_generate().unwrap_or_else(|err| panic!("{:?}", err))
+	}
+
+	pub fn try_generate(&self) -> Result<i64, BaseError> {
+		if self.is_revoked() {
+			return Err(BaseError::InvalidNodeId(format!("node {} is revoked", self.node())));
 		}
 
-		self.get_snowflake()
+		// * Relaxed ordering suffices as uniqueness only depends on modification order of the single word
+		let mut current = self.state.load(Ordering::Relaxed);
+		loop {
+			let last_millis = current >> SEQUENCE_BITS;
+			let now_millis = self.now_millis();
+
+			let next = if now_millis > last_millis {
+				now_millis << SEQUENCE_BITS
+			} else if now_millis < last_millis && self.on_clock_regression == ClockRegression::Fail {
+				return Err(BaseError::ClockRegressed(format!("clock is {}ms behind the last id", last_millis - now_millis)));
+			} else if current & SEQUENCE_MASK < SEQUENCE_MASK {
+				// * Same millisecond, or clock behind whose ids keep taking the last timestamp
+				current + 1
+			} else if now_millis < last_millis {
+				// * Sequence of the last timestamp ran out while clock is behind, so sleep until it catches up
+				std::thread::sleep(Duration::from_millis((last_millis - now_millis) as u64));
+				current = self.state.load(Ordering::Relaxed);
+				continue;
+			} else {
+				std::thread::yield_now();
+				current = self.state.load(Ordering::Relaxed);
+				continue;
+			};
+
+			match self.state.compare_exchange_weak(current, next, Ordering::Relaxed, Ordering::Relaxed) {
+				Ok(_) => return Ok(self.get_snowflake(next)),
+				Err(actual) => current = actual,
+			}
+		}
 	}
 }
 
@@ -195,31 +335,82 @@ pub fn current_time_in_milli(epoch: SystemTime) -> i64 {
 	SystemTime::now().duration_since(epoch).expect("System Time Error!").as_millis() as i64
 }
 
-#[inline(always)]
-// Constantly refreshing the latest milliseconds by busy waiting.
-fn race_next_milli(timestamp: i64, epoch: SystemTime) -> i64 {
-	let mut latest_time_millis: i64;
-	loop {
-		latest_time_millis = current_time_in_milli(epoch);
-		if latest_time_millis > timestamp {
-			return latest_time_millis;
-		}
-		spin_loop();
-	}
-}
+static ID_GENERATOR: std::sync::OnceLock<NumericalUniqueIdGenerator> = std::sync::OnceLock::new();
 
-static ID_GENERATOR: std::sync::LazyLock<NumericalUniqueIdGenerator> = std::sync::LazyLock::new(|| {
-	NumericalUniqueIdGenerator::new(
-		std::env::var("DATACENTER_ID").unwrap_or("1".to_string()).parse::<i32>().expect("Parsing Failed!"),
-		std::env::var("MACHINE_ID").unwrap_or("1".to_string()).parse::<i32>().expect("Parsing Failed!"),
-	)
-});
+// * Node is taken from environment unless configured before the first id is generated
+fn id_generator() -> &'static NumericalUniqueIdGenerator {
+	ID_GENERATOR.get_or_init(|| NumericalUniqueIdGenerator::with_node(NodeId::from_env().expect("Invalid Node Identity In Environment!")))
+}
 
 #[derive(Clone, Hash, PartialEq, Debug, Eq, Ord, PartialOrd, Copy, Default)]
 pub struct SnowFlake(pub i64);
 impl SnowFlake {
 	pub fn generate() -> Self {
-		ID_GENERATOR.generate().into()
+		id_generator().generate().into()
+	}
+
+	pub fn try_generate() -> Result<Self, BaseError> {
+		id_generator().try_generate().map(Self)
+	}
+
+	/// Set node identity [SnowFlake::generate] uses. It must be called at startup, before any id is generated;
+	/// otherwise it fails unless the node in use is the same one.
+	/// ## Example
+	/// ```rust,no_run
+	/// SnowFlake::configure(NodeId::new(1, 7)?)?;
+	/// ```
+	pub fn configure(node: NodeId) -> Result<(), BaseError> {
+		let in_use = ID_GENERATOR.get_or_init(|| NumericalUniqueIdGenerator::with_node(node)).node();
+		if in_use != node {
+			return Err(BaseError::InvalidNodeId(format!("node {} is already in use, can't be changed to {}", in_use, node)));
+		}
+		Ok(())
+	}
+
+	/// Node identity [SnowFlake::generate] uses.
+	pub fn node() -> NodeId {
+		id_generator().node()
+	}
+
+	/// Stop [SnowFlake::generate] from generating ids if it uses the given node, see [NumericalUniqueIdGenerator::revoke].
+	pub fn revoke(node: NodeId) {
+		if let Some(id_generator) = ID_GENERATOR.get().filter(|id_generator| id_generator.node() == node) {
+			id_generator.revoke();
+		}
+	}
+	/// Time the id was generated at. Ids are taken as generated with UNIX epoch, as [SnowFlake::generate] does.
+	pub fn timestamp(&self) -> DateTime<Utc> {
+		DateTime::from_timestamp_millis(self.0 >> TIMESTAMP_SHIFT).expect("Timestamp Out Of Range!")
+	}
+
+	pub fn datacenter_id(&self) -> i32 {
+		((self.0 >> DATACENTER_SHIFT) & NODE_MASK) as i32
+	}
+
+	pub fn machine_id(&self) -> i32 {
+		((self.0 >> MACHINE_SHIFT) & NODE_MASK) as i32
+	}
+
+	/// Position of the id among the ones generated in the same millisecond on the same node.
+	pub fn sequence(&self) -> i32 {
+		(self.0 & SEQUENCE_MASK) as i32
+	}
+
+	/// Smallest id that can be generated at the instant, truncated to milliseconds.
+	/// Together with [SnowFlake::max_at], it bounds ids generated in a time range.
+	/// ## Example
+	/// ```rust,no_run
+	/// sqlx::query_as("SELECT * FROM service_outbox WHERE id BETWEEN $1 AND $2")
+	///     .bind(*SnowFlake::min_at(from))
+	///     .bind(*SnowFlake::max_at(to))
+	/// ```
+	pub fn min_at(instant: DateTime<Utc>) -> Self {
+		Self(instant.timestamp_millis() << TIMESTAMP_SHIFT)
+	}
+
+	/// Largest id that can be generated at the instant, truncated to milliseconds.
+	pub fn max_at(instant: DateTime<Utc>) -> Self {
+		Self(instant.timestamp_millis() << TIMESTAMP_SHIFT | ((1 << TIMESTAMP_SHIFT) - 1))
 	}
 }
 
@@ -345,9 +536,128 @@ fn test_generate_not_sequential_value_when_sleep() {
 	assert_ne!(first + 1, second);
 }
 
+#[test]
+fn test_concurrent_generate_is_unique() {
+	let id_generator = Arc::new(NumericalUniqueIdGenerator::new(1, 2));
+	let handles = (0..8)
+		.map(|_| {
+			let id_generator = Arc::clone(&id_generator);
+			std::thread::spawn(move || (0..200_000).map(|_| id_generator.generate()).collect::<Vec<_>>())
+		})
+		.collect::<Vec<_>>();
+
+	let mut ids = vec![];
+	for handle in handles {
+		let generated = handle.join().unwrap();
+		// * Ids are increasing on each thread
+		assert!(generated.windows(2).all(|w| w[0] < w[1]));
+		ids.extend(generated);
+	}
+	let generated = ids.len();
+	ids.sort();
+	ids.dedup();
+	assert_eq!(ids.len(), generated);
+}
+
+#[test]
+fn test_clock_regression() {
+	let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+	// * Ids keep increasing on the last timestamp while clock is behind
+	let clock = Arc::new(crate::prelude::FixedClock::new(start));
+	let id_generator = NumericalUniqueIdGenerator::new(1, 2).with_clock(Arc::clone(&clock));
+	let first = id_generator.generate();
+	clock.set(start - chrono::Duration::milliseconds(5));
+	let second = id_generator.generate();
+	assert_eq!(second, first + 1);
+
+	// * Once sequence of the last timestamp runs out, it waits for the clock to pass it
+	for _ in 0..SEQUENCE_MASK - 1 {
+		id_generator.generate();
+	}
+	let waiting = std::thread::spawn({
+		let clock = Arc::clone(&clock);
+		move || {
+			std::thread::sleep(Duration::from_millis(20));
+			clock.set(start + chrono::Duration::milliseconds(1));
+		}
+	});
+	let next: SnowFlake = id_generator.generate().into();
+	waiting.join().unwrap();
+	assert_eq!((next.timestamp(), next.sequence()), (start + chrono::Duration::milliseconds(1), 0));
+
+	// * Or fail when told to
+	let clock = Arc::new(crate::prelude::FixedClock::new(start));
+	let id_generator = NumericalUniqueIdGenerator::new(1, 2).with_clock(Arc::clone(&clock)).on_clock_regression(ClockRegression::Fail);
+	let first = id_generator.try_generate().unwrap();
+	clock.set(start - chrono::Duration::milliseconds(5));
+	assert!(matches!(id_generator.try_generate(), Err(BaseError::ClockRegressed(msg)) if msg == "clock is 5ms behind the last id"));
+	clock.set(start);
+	assert_eq!(id_generator.try_generate().unwrap(), first + 1);
+}
+
+#[test]
+fn test_decompose() {
+	let generated_at = Utc::now();
+	let id: SnowFlake = NumericalUniqueIdGenerator::new(3, 17).with_clock(crate::prelude::FixedClock::new(generated_at)).generate().into();
+	assert_eq!(id.timestamp(), DateTime::from_timestamp_millis(generated_at.timestamp_millis()).unwrap());
+	assert_eq!((id.datacenter_id(), id.machine_id(), id.sequence()), (3, 17, 0));
+
+	let id = SnowFlake(1 << TIMESTAMP_SHIFT | 31 << DATACENTER_SHIFT | 31 << MACHINE_SHIFT | 4095);
+	assert_eq!(id.timestamp(), DateTime::from_timestamp_millis(1).unwrap());
+	assert_eq!((id.datacenter_id(), id.machine_id(), id.sequence()), (31, 31, 4095));
+}
+
+#[test]
+fn test_min_and_max_at_bound_ids_of_instant() {
+	let instant = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+	let (min, max) = (SnowFlake::min_at(instant), SnowFlake::max_at(instant));
+	assert_eq!((min.timestamp(), max.timestamp()), (instant, instant));
+	assert_eq!((min.datacenter_id(), min.machine_id(), min.sequence()), (0, 0, 0));
+	assert_eq!((max.datacenter_id(), max.machine_id(), max.sequence()), (31, 31, 4095));
+
+	let id: SnowFlake = NumericalUniqueIdGenerator::new(1, 2).with_clock(crate::prelude::FixedClock::new(instant)).generate().into();
+	assert!(min < id && id < max);
+	assert!(SnowFlake::max_at(instant - chrono::Duration::milliseconds(1)) < min);
+	assert!(max < SnowFlake::min_at(instant + chrono::Duration::milliseconds(1)));
+}
+
+#[test]
+fn test_node_id_bounds() {
+	assert_eq!(NodeId::new(0, 31).unwrap(), NodeId { datacenter_id: 0, machine_id: 31 });
+	assert!(matches!(NodeId::new(32, 1), Err(BaseError::InvalidNodeId(msg)) if msg.starts_with("datacenter_id")));
+	assert!(matches!(NodeId::new(1, -1), Err(BaseError::InvalidNodeId(msg)) if msg.starts_with("machine_id")));
+
+	assert_eq!(NodeId::parse(None, Some(" 3 ")).unwrap(), NodeId { datacenter_id: 1, machine_id: 3 });
+	assert!(matches!(NodeId::parse(Some("a"), None), Err(BaseError::InvalidNodeId(msg)) if msg.starts_with("DATACENTER_ID")));
+	assert!(NodeId::parse(Some("1"), Some("40")).is_err());
+}
+
+#[test]
+fn test_revoked_generator_stops_generating() {
+	let id_generator = NumericalUniqueIdGenerator::new(1, 2);
+	assert!(id_generator.try_generate().is_ok());
+	id_generator.revoke();
+	assert!(matches!(id_generator.try_generate(), Err(BaseError::InvalidNodeId(msg)) if msg.contains("revoked")));
+}
+
+#[test]
+#[should_panic(expected = "machine_id must be between 0 and 31")]
+fn test_generator_rejects_node_out_of_bounds() {
+	NumericalUniqueIdGenerator::new(1, 32);
+}
+
+#[test]
+fn test_configure_after_generate_keeps_node_in_use() {
+	SnowFlake::generate();
+	let node = SnowFlake::node();
+	assert!(SnowFlake::configure(node).is_ok());
+	assert!(SnowFlake::configure(NodeId::new(node.datacenter_id, (node.machine_id + 1) % 32).unwrap()).is_err());
+}
+
 #[test]
 fn test_singleton_generate() {
-	let id_generator = &ID_GENERATOR;
+	let id_generator = id_generator();
 	let mut ids = Vec::with_capacity(1000000);
 
 	for _ in 0..99 {
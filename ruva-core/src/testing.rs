@@ -0,0 +1,480 @@
+//! ### Testing
+//! Given-when-then harness for command handlers and aggregates, enabled by `testing` feature.
+//!
+//! Command handler is run against in-memory [Context] backed by [InMemoryConnection], in which events given
+//! beforehand and outboxes written by the handler are kept. Assertions on failure print diff of expected and actual.
+//!
+//! [RecordingBus] wraps message bus to record commands, queued events and event handlers invoked,
+//! so that choreography of handlers registered with `init_event_handler!` can be asserted on.
+//!
+//! ```rust,no_run
+//! Scenario::new()
+//!     .given([OrderPlaced { id: 1 }.to_message()])
+//!     .when(CancelOrder { id: 1 }, cancel_order)
+//!     .await
+//!     .then_ok()
+//!     .then_events([OrderCancelled { id: 1 }]);
+//!
+//! AggregateScenario::given_state(Order::default())
+//!     .when(|order| order.cancel())
+//!     .then_error(OrderError::NotPlaced);
+//!
+//! let bus = RecordingBus::<OrderError, _>::new(MessageBus).disable("OrderShipped");
+//! bus.execute_and_wait(PlaceOrder { id: 1 }, Arc::new(InMemoryConnection::default())).await?;
+//! assert_eq!(bus.topics(), vec!["OrderPlaced", "StockReserved"]);
+//! ```
+//!
+//! [SimulatedBus] runs the same handlers under scheduler driven by seed to reproduce interleavings of them.
+
+mod simulation;
+
+pub use simulation::{fuzz, SimulatedBus, Step, SIMULATION_SEED};
+
+use crate::bus_components::messagebus::handle_event;
+use crate::prelude::{
+	ApplicationError, ApplicationResponse, AtomicContextManager, BaseError, Context, ContextManager, EventHandlers, Future, HandlerMapper, Handlers, OutBox, TAggregate, TClock, TCodec, TCommand,
+	TCommandService, TConnection, TEvent, TEventBus, TEventHandler, TEventSourced, TIdGenerator, TJoinedEventHandler, TMessageBus,
+};
+use pretty_assertions::assert_eq;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Connection keeping outboxes in memory, in place of database.
+#[derive(Default)]
+pub struct InMemoryConnection {
+	outboxes: Mutex<Vec<OutBox>>,
+}
+
+impl TConnection for InMemoryConnection {}
+
+impl InMemoryConnection {
+	/// Connection of the context, if it is in-memory one.
+	pub fn of(context: &Context) -> Option<&InMemoryConnection> {
+		context.super_ctx.conn.downcast_ref::<InMemoryConnection>()
+	}
+
+	pub fn outboxes(&self) -> Vec<OutBox> {
+		self.outboxes.lock().unwrap().clone()
+	}
+
+	/// Rebuild aggregate from the outboxes of it kept so far.
+	pub fn load<A: TEventSourced>(&self, aggregate_id: impl ToString) -> Result<A, BaseError> {
+		let aggregate_id = aggregate_id.to_string();
+		let mut aggregate = A::default();
+		for outbox in self
+			.outboxes
+			.lock()
+			.unwrap()
+			.iter()
+			.filter(|o| o.aggregate_name == A::aggregate_name() && o.aggregate_id == aggregate_id)
+		{
+			aggregate.apply(outbox)?;
+		}
+		Ok(aggregate)
+	}
+
+	/// Keep outboxes, assigning sequences among the ones of the same aggregate as database does.
+	pub fn record(&self, outboxes: impl IntoIterator<Item = OutBox>) {
+		let mut recorded = self.outboxes.lock().unwrap();
+		for mut outbox in outboxes {
+			let last = recorded
+				.iter()
+				.filter(|o| o.aggregate_name == outbox.aggregate_name && o.aggregate_id == outbox.aggregate_id)
+				.map(|o| o.sequence)
+				.max();
+			outbox.sequence = last.unwrap_or(0) + 1;
+			recorded.push(outbox);
+		}
+	}
+}
+
+/// Scenario of command handler.
+pub struct Scenario {
+	connection: Arc<InMemoryConnection>,
+	context_manager: ContextManager,
+}
+
+impl Default for Scenario {
+	fn default() -> Self {
+		let connection = Arc::new(InMemoryConnection::default());
+		Self {
+			context_manager: ContextManager::new(connection.clone()),
+			connection,
+		}
+	}
+}
+
+impl Scenario {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Events that happened before, kept as outboxes of [InMemoryConnection].
+	pub fn given(self, events: impl IntoIterator<Item = Arc<dyn TEvent>>) -> Self {
+		// * Past events don't take ids nor time from those of the scenario
+		let context_manager = ContextManager::new(self.connection.clone());
+		self.connection
+			.record(events.into_iter().map(|event| context_manager.outbox(event.as_ref()).expect("Event Must Be Encodable")));
+		self
+	}
+
+	/// Codec of the bus outboxes are encoded with.
+	pub fn with_codec(mut self, codec: impl TCodec + 'static) -> Self {
+		self.context_manager.codec = Some(Arc::new(codec));
+		self
+	}
+
+	/// Clock the handler and outboxes tell time with.
+	pub fn with_clock(mut self, clock: impl TClock + 'static) -> Self {
+		self.context_manager = self.context_manager.with_clock(clock);
+		self
+	}
+
+	/// Generator of ids the handler and outboxes use.
+	pub fn with_id_generator(mut self, id_generator: impl TIdGenerator + 'static) -> Self {
+		self.context_manager = self.context_manager.with_id_generator(id_generator);
+		self
+	}
+
+	/// Run handler with in-memory context. Outboxes are written only when it succeeds, as the transaction would commit.
+	pub async fn when<C, R, E>(self, command: C, handler: impl AsyncFnOnce(C, &mut Context) -> Result<R, E>) -> Then<R, E> {
+		let mut context = Context::new(Arc::new(self.context_manager));
+
+		let result = handler(command, &mut context).await;
+
+		let events: Vec<_> = std::mem::take(&mut context.curr_events).into();
+		let outboxes = events
+			.iter()
+			.filter(|e| e.externally_notifiable())
+			.map(|e| context.super_ctx.outbox(e.as_ref()).expect("Event Must Be Encodable"))
+			.collect::<Vec<_>>();
+		if result.is_ok() {
+			self.connection.record(outboxes.clone());
+		}
+
+		Then { result, events, outboxes, state: () }
+	}
+}
+
+/// Scenario of aggregate.
+pub struct AggregateScenario<A> {
+	aggregate: A,
+}
+
+impl<A: TAggregate> AggregateScenario<A> {
+	pub fn given_state(aggregate: A) -> Self {
+		Self { aggregate }
+	}
+
+	/// Call method of aggregate, collecting events it raises.
+	pub fn when<R, E>(mut self, f: impl FnOnce(&mut A) -> Result<R, E>) -> Then<R, E, A> {
+		let result = f(&mut self.aggregate);
+		let events: Vec<_> = self.aggregate.take_events().into();
+		let context_manager = ContextManager::new(Arc::new(InMemoryConnection::default()));
+		let outboxes = events
+			.iter()
+			.filter(|e| e.externally_notifiable())
+			.map(|e| context_manager.outbox(e.as_ref()).expect("Event Must Be Encodable"))
+			.collect();
+		Then {
+			result,
+			events,
+			outboxes,
+			state: self.aggregate,
+		}
+	}
+}
+
+impl<A: TEventSourced> AggregateScenario<A> {
+	/// Aggregate rebuilt from events that happened before.
+	pub fn given(events: impl IntoIterator<Item = Arc<dyn TEvent>>) -> Self {
+		let connection = Arc::new(InMemoryConnection::default());
+		let context_manager = ContextManager::new(connection.clone());
+		connection.record(events.into_iter().map(|event| context_manager.outbox(event.as_ref()).expect("Event Must Be Encodable")));
+
+		let mut aggregate = A::default();
+		for outbox in connection.outboxes() {
+			aggregate.apply(&outbox).expect("Given Event Must Be Applicable");
+		}
+		Self { aggregate }
+	}
+}
+
+/// Outcome of scenario to assert on.
+pub struct Then<R, E, S = ()> {
+	pub result: Result<R, E>,
+	/// Events raised, in order.
+	pub events: Vec<Arc<dyn TEvent>>,
+	/// Outboxes of externally notifiable events raised.
+	pub outboxes: Vec<OutBox>,
+	/// State of aggregate after [AggregateScenario::when].
+	pub state: S,
+}
+
+impl<R: Debug, E: Debug, S> Then<R, E, S> {
+	pub fn then_ok(self) -> Self {
+		if let Err(err) = &self.result {
+			panic!("Expected Success But Failed: {:?}", err);
+		}
+		self
+	}
+
+	pub fn then_response(self, expected: R) -> Self
+	where
+		R: PartialEq,
+	{
+		match &self.result {
+			Ok(response) => assert_eq!(response, &expected),
+			Err(err) => panic!("Expected Success But Failed: {:?}", err),
+		}
+		self
+	}
+
+	pub fn then_error(self, expected: E) -> Self
+	where
+		E: PartialEq,
+	{
+		match &self.result {
+			Ok(response) => panic!("Expected Failure But Succeeded: {:?}", response),
+			Err(err) => assert_eq!(err, &expected),
+		}
+		self
+	}
+
+	/// Assert that events of the type are raised, and nothing else.
+	pub fn then_events<T: TEvent + PartialEq + Debug>(self, expected: impl IntoIterator<Item = T>) -> Self {
+		let actual = self
+			.events
+			.iter()
+			.map(|event| event.downcast_ref::<T>().unwrap_or_else(|| panic!("Unexpected Event Raised: {:?}", event)))
+			.collect::<Vec<_>>();
+		let expected = expected.into_iter().collect::<Vec<_>>();
+		assert_eq!(actual, expected.iter().collect::<Vec<_>>());
+		self
+	}
+
+	pub fn then_no_events(self) -> Self {
+		assert_eq!(self.events.iter().map(|event| format!("{:?}", event)).collect::<Vec<_>>(), Vec::<String>::new());
+		self
+	}
+
+	/// Assert on topic and payload of outboxes written.
+	pub fn then_outboxes<'a>(self, expected: impl IntoIterator<Item = (&'a str, serde_json::Value)>) -> Self {
+		let actual = self
+			.outboxes
+			.iter()
+			.map(|outbox| (outbox.topic.as_str(), outbox.value().expect("Outbox Must Be Decodable")))
+			.collect::<Vec<_>>();
+		assert_eq!(actual, expected.into_iter().collect::<Vec<_>>());
+		self
+	}
+
+	pub fn then_state(self, f: impl FnOnce(&S)) -> Self {
+		f(&self.state);
+		self
+	}
+
+	pub fn into_result(self) -> Result<R, E> {
+		self.result
+	}
+}
+
+/// What [RecordingBus] has seen, in order.
+#[derive(Debug, Clone)]
+pub enum Record {
+	/// Command dispatched, formatted with `Debug`.
+	Command(String),
+	/// Event pushed to the queue of [ContextManager].
+	Event(Arc<dyn TEvent>),
+	/// Event handler invoked, with its position among the handlers of the topic.
+	Handler { topic: String, index: usize },
+}
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<(Vec<Record>, HashSet<usize>)>>);
+
+impl Recorder {
+	fn push(&self, record: Record) {
+		self.0.lock().unwrap().0.push(record);
+	}
+
+	// * Events are told apart by address, which is not reused as recorded events are kept alive
+	fn event(&self, event: &Arc<dyn TEvent>) {
+		let mut recorded = self.0.lock().unwrap();
+		if recorded.1.insert(Arc::as_ptr(event) as *const () as usize) {
+			recorded.0.push(Record::Event(Arc::clone(event)));
+		}
+	}
+
+	fn scan(&self, context_manager: &ContextManager) {
+		context_manager.event_queue.iter().for_each(|event| self.event(event));
+	}
+}
+
+type EventHandler<E> = Box<dyn Fn(Arc<dyn TEvent>, AtomicContextManager) -> Future<E> + Send + Sync>;
+
+/// Test double of message bus that records commands dispatched, events queued and event handlers invoked.
+///
+/// It wraps bus whose handlers are registered as usual, for example [MessageBus](crate::prelude::MessageBus)
+/// with `init_event_handler!`, and dispatches through them unless they are stubbed or disabled by topic.
+/// Handlers joining the transaction of the command are run as they are, without being recorded.
+///
+/// Map of wrapped handlers is leaked to be `'static` as the bus requires, so create one per test.
+pub struct RecordingBus<E: 'static, B> {
+	inner: B,
+	recorder: Recorder,
+	stubs: Mutex<HashMap<String, Vec<EventHandler<E>>>>,
+	event_handler: OnceLock<&'static TEventHandler<E>>,
+}
+
+impl<E: 'static, B: TEventBus<E>> RecordingBus<E, B> {
+	pub fn new(inner: B) -> Self {
+		Self {
+			inner,
+			recorder: Recorder::default(),
+			stubs: Default::default(),
+			event_handler: OnceLock::new(),
+		}
+	}
+
+	/// Replace handlers of the topic with the given one.
+	pub fn stub(self, topic: impl Into<String>, handler: impl Fn(Arc<dyn TEvent>, AtomicContextManager) -> Future<E> + Send + Sync + 'static) -> Self {
+		self.stubs.lock().unwrap().insert(topic.into(), vec![Box::new(handler)]);
+		self
+	}
+
+	/// Don't invoke handlers of the topic. Events of the topic are still recorded.
+	pub fn disable(self, topic: impl Into<String>) -> Self {
+		self.stubs.lock().unwrap().insert(topic.into(), vec![]);
+		self
+	}
+
+	pub fn records(&self) -> Vec<Record> {
+		self.recorder.0.lock().unwrap().0.clone()
+	}
+
+	pub fn commands(&self) -> Vec<String> {
+		self.records()
+			.into_iter()
+			.filter_map(|record| if let Record::Command(command) = record { Some(command) } else { None })
+			.collect()
+	}
+
+	pub fn events(&self) -> Vec<Arc<dyn TEvent>> {
+		self.records()
+			.into_iter()
+			.filter_map(|record| if let Record::Event(event) = record { Some(event) } else { None })
+			.collect()
+	}
+
+	/// Topics of events queued, in order.
+	pub fn topics(&self) -> Vec<String> {
+		self.events().iter().map(|event| event.metadata().topic).collect()
+	}
+
+	/// Topic and position of handlers invoked, in order.
+	pub fn handlers(&self) -> Vec<(String, usize)> {
+		self.records()
+			.into_iter()
+			.filter_map(|record| if let Record::Handler { topic, index } = record { Some((topic, index)) } else { None })
+			.collect()
+	}
+
+	/// Handle event as if it were raised by command, without running any command.
+	pub async fn publish(&self, event: Arc<dyn TEvent>, conn: Arc<dyn TConnection>) -> Result<(), E>
+	where
+		E: ApplicationError + From<BaseError>,
+		BaseError: From<E>,
+	{
+		let context_manager = Arc::new(ContextManager::new(conn).with_joined_event_handler(self.joined_event_handler()));
+		self.recorder.event(&event);
+		handle_event(event, context_manager, self.event_handler()).await.map(|_| ())
+	}
+
+	fn record_handler(&self, topic: &str, index: usize, handler: EventHandler<E>) -> EventHandler<E> {
+		let (recorder, topic) = (self.recorder.clone(), topic.to_string());
+		Box::new(move |event, context_manager| {
+			recorder.event(&event);
+			recorder.scan(&context_manager);
+			recorder.push(Record::Handler { topic: topic.clone(), index });
+
+			let fut = handler(event, Arc::clone(&context_manager));
+			let recorder = recorder.clone();
+			Box::pin(async move {
+				let res = fut.await;
+				recorder.scan(&context_manager);
+				res
+			})
+		})
+	}
+}
+
+impl<E: 'static, B: TEventBus<E>> TEventBus<E> for RecordingBus<E, B> {
+	fn event_handler(&self) -> &'static TEventHandler<E> {
+		self.event_handler.get_or_init(|| {
+			let mut stubs = std::mem::take(&mut *self.stubs.lock().unwrap());
+			let mut map: TEventHandler<E> = HandlerMapper::new();
+
+			for (topic, handlers) in self.inner.event_handler().iter() {
+				let mut recorded = |handlers: &'static Handlers<E>| -> Handlers<E> {
+					match stubs.remove(topic) {
+						Some(stubs) => stubs.into_iter().enumerate().map(|(i, stub)| self.record_handler(topic, i, stub)).collect(),
+						None => handlers
+							.iter()
+							.enumerate()
+							.map(|(i, handler)| self.record_handler(topic, i, Box::new(move |event, context_manager| handler(event, context_manager))))
+							.collect(),
+					}
+				};
+				let handlers = match handlers {
+					EventHandlers::Sync(handlers) => EventHandlers::Sync(recorded(handlers)),
+					EventHandlers::Async(handlers) => EventHandlers::Async(recorded(handlers)),
+				};
+				map.insert(topic.clone(), handlers);
+			}
+			// * Topics without registered handler can be stubbed too
+			for (topic, stubs) in stubs {
+				let handlers = stubs.into_iter().enumerate().map(|(i, stub)| self.record_handler(&topic, i, stub)).collect();
+				map.insert(topic, EventHandlers::Sync(handlers));
+			}
+			Box::leak(Box::new(map))
+		})
+	}
+
+	fn joined_event_handler(&self) -> Option<&'static TJoinedEventHandler> {
+		self.inner.joined_event_handler()
+	}
+}
+
+impl<R, E, C, B> TMessageBus<R, E, C> for RecordingBus<E, B>
+where
+	BaseError: From<E>,
+	R: ApplicationResponse,
+	E: ApplicationError + From<BaseError> + 'static,
+	C: TCommand,
+	B: TMessageBus<R, E, C>,
+{
+	fn command_handler(&self, context_manager: AtomicContextManager, cmd: C) -> impl TCommandService<R, E> {
+		self.recorder.push(Record::Command(format!("{:?}", cmd)));
+		RecordedService {
+			service: self.inner.command_handler(Arc::clone(&context_manager), cmd),
+			context_manager,
+			recorder: self.recorder.clone(),
+		}
+	}
+}
+
+struct RecordedService<S> {
+	service: S,
+	context_manager: AtomicContextManager,
+	recorder: Recorder,
+}
+
+impl<R, E, S: TCommandService<R, E>> TCommandService<R, E> for RecordedService<S> {
+	async fn execute(self) -> Result<R, E> {
+		let res = self.service.execute().await;
+		self.recorder.scan(&self.context_manager);
+		res
+	}
+}
@@ -0,0 +1,323 @@
+//! ### Simulation
+//! [SimulatedBus] runs command and event processing under scheduler driven by seed. For each seed, event handlers
+//! are delayed before they start, yield to their siblings while they run and fail in place of running, so that
+//! handlers in `#[async]` group interleave differently from seed to seed while the same seed always replays
+//! the same interleaving. Handlers are expected to be deterministic themselves, for example by running against
+//! [InMemoryConnection](super::InMemoryConnection) with [FixedClock](crate::prelude::FixedClock).
+//!
+//! [fuzz] runs check for many seeds and reports the first one it fails on, which can be replayed by setting
+//! `RUVA_SIMULATION_SEED`.
+//!
+//! ```rust,no_run
+//! fuzz(0..100, async |seed| {
+//!     let bus = SimulatedBus::<OrderError, _>::new(MessageBus, seed).failure_rate(0.1);
+//!     bus.execute_and_wait(PlaceOrder { id: 1 }, Arc::new(InMemoryConnection::default())).await.unwrap();
+//!     assert_invariants();
+//! })
+//! .await;
+//! ```
+
+use super::EventHandler;
+use crate::bus_components::messagebus::handle_event;
+use crate::prelude::{
+	ApplicationError, ApplicationResponse, AtomicContextManager, BaseError, ContextManager, EventHandlers, HandlerMapper, TCommand, TCommandService, TConnection, TEvent, TEventBus, TEventHandler,
+	TJoinedEventHandler, TMessageBus,
+};
+use futures::FutureExt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+
+/// Environment variable that, when set, makes [fuzz] run only the given seed.
+pub const SIMULATION_SEED: &str = "RUVA_SIMULATION_SEED";
+
+/// Decision made by the scheduler of [SimulatedBus], in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+	/// Handler is held back for the given number of scheduler ticks before it starts.
+	Delayed {
+		topic: String,
+		index: usize,
+		ticks: u32,
+	},
+	/// Handler yielded to its siblings while running.
+	Yielded {
+		topic: String,
+		index: usize,
+	},
+	/// Handler failed with [BaseError::InjectedFailure] in place of running.
+	Failed {
+		topic: String,
+		index: usize,
+	},
+	Started {
+		topic: String,
+		index: usize,
+	},
+	Finished {
+		topic: String,
+		index: usize,
+	},
+}
+
+// * SplitMix64, good enough to spread decisions and stable across platforms and releases
+struct Rng(u64);
+
+impl Rng {
+	fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z ^ (z >> 31)
+	}
+
+	fn chance(&mut self, rate: f64) -> bool {
+		((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < rate
+	}
+
+	fn below(&mut self, bound: u32) -> u32 {
+		match bound {
+			0 => 0,
+			bound => (self.next_u64() % bound as u64) as u32,
+		}
+	}
+}
+
+struct Scheduler {
+	rng: Mutex<Rng>,
+	trace: Mutex<Vec<Step>>,
+	failure_rate: f64,
+	yield_rate: f64,
+	max_delay: u32,
+}
+
+impl Scheduler {
+	fn step(&self, step: Step) {
+		self.trace.lock().unwrap().push(step);
+	}
+}
+
+/// Future polled only when the scheduler lets it, waking itself right away otherwise.
+struct Scheduled<F> {
+	fut: Pin<Box<F>>,
+	scheduler: Arc<Scheduler>,
+	topic: String,
+	index: usize,
+	ticks: u32,
+	started: bool,
+}
+
+impl<F: Future> Future for Scheduled<F> {
+	type Output = F::Output;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let this = self.get_mut();
+		if this.ticks > 0 {
+			this.ticks -= 1;
+			cx.waker().wake_by_ref();
+			return Poll::Pending;
+		}
+		if !this.started {
+			this.started = true;
+			this.scheduler.step(Step::Started {
+				topic: this.topic.clone(),
+				index: this.index,
+			});
+		} else if this.scheduler.rng.lock().unwrap().chance(this.scheduler.yield_rate) {
+			this.scheduler.step(Step::Yielded {
+				topic: this.topic.clone(),
+				index: this.index,
+			});
+			cx.waker().wake_by_ref();
+			return Poll::Pending;
+		}
+		let res = this.fut.as_mut().poll(cx);
+		if res.is_ready() {
+			this.scheduler.step(Step::Finished {
+				topic: this.topic.clone(),
+				index: this.index,
+			});
+		}
+		res
+	}
+}
+
+/// Message bus whose command and event processing is scheduled by seed.
+///
+/// It wraps bus whose handlers are registered as usual, as [RecordingBus](super::RecordingBus) does. Injected failures
+/// apply to event handlers only; command is delayed and interleaved but always runs.
+/// Map of wrapped handlers is leaked to be `'static` as the bus requires, so create one per run.
+pub struct SimulatedBus<E: 'static, B> {
+	inner: B,
+	seed: u64,
+	scheduler: Arc<Scheduler>,
+	event_handler: OnceLock<&'static TEventHandler<E>>,
+}
+
+impl<E: 'static, B: TEventBus<E>> SimulatedBus<E, B> {
+	pub fn new(inner: B, seed: u64) -> Self {
+		Self {
+			inner,
+			seed,
+			scheduler: Arc::new(Scheduler {
+				rng: Mutex::new(Rng(seed)),
+				trace: Default::default(),
+				failure_rate: 0.0,
+				yield_rate: 0.5,
+				max_delay: 4,
+			}),
+			event_handler: OnceLock::new(),
+		}
+	}
+
+	/// Probability of event handler failing in place of running. `0.0` by default.
+	pub fn failure_rate(mut self, rate: f64) -> Self {
+		self.scheduler_mut().failure_rate = rate;
+		self
+	}
+
+	/// Probability of handler yielding to its siblings each time it is polled. `0.5` by default.
+	pub fn yield_rate(mut self, rate: f64) -> Self {
+		self.scheduler_mut().yield_rate = rate;
+		self
+	}
+
+	/// Upper bound of scheduler ticks handler is held back for before it starts. `4` by default.
+	pub fn max_delay(mut self, ticks: u32) -> Self {
+		self.scheduler_mut().max_delay = ticks;
+		self
+	}
+
+	pub fn seed(&self) -> u64 {
+		self.seed
+	}
+
+	/// Decisions made so far, which are the same for the same seed.
+	pub fn trace(&self) -> Vec<Step> {
+		self.scheduler.trace.lock().unwrap().clone()
+	}
+
+	/// Handle event as if it were raised by command, without running any command.
+	pub async fn publish(&self, event: Arc<dyn TEvent>, conn: Arc<dyn TConnection>) -> Result<(), E>
+	where
+		E: ApplicationError + From<BaseError>,
+		BaseError: From<E>,
+	{
+		let context_manager = Arc::new(ContextManager::new(conn).with_joined_event_handler(self.joined_event_handler()));
+		handle_event(event, context_manager, self.event_handler()).await.map(|_| ())
+	}
+
+	fn scheduler_mut(&mut self) -> &mut Scheduler {
+		Arc::get_mut(&mut self.scheduler).expect("Scheduler Must Be Configured Before Use")
+	}
+
+	fn simulate_handler(&self, topic: &str, index: usize, handler: &'static EventHandler<E>) -> EventHandler<E>
+	where
+		E: From<BaseError>,
+	{
+		let (scheduler, topic, seed) = (Arc::clone(&self.scheduler), topic.to_string(), self.seed);
+		Box::new(move |event, context_manager| {
+			let fails = scheduler.rng.lock().unwrap().chance(scheduler.failure_rate);
+			let failure = fails.then(|| {
+				scheduler.step(Step::Failed { topic: topic.clone(), index });
+				format!("{}[{}] with seed {}", topic, index, seed)
+			});
+			Box::pin(schedule(&scheduler, &topic, index, async move {
+				match failure {
+					Some(failure) => Err(BaseError::InjectedFailure(failure).into()),
+					None => handler(event, context_manager).await,
+				}
+			}))
+		})
+	}
+}
+
+fn schedule<F: Future>(scheduler: &Arc<Scheduler>, topic: &str, index: usize, fut: F) -> Scheduled<F> {
+	let ticks = scheduler.rng.lock().unwrap().below(scheduler.max_delay + 1);
+	if ticks > 0 {
+		scheduler.step(Step::Delayed {
+			topic: topic.to_string(),
+			index,
+			ticks,
+		});
+	}
+	Scheduled {
+		fut: Box::pin(fut),
+		scheduler: Arc::clone(scheduler),
+		topic: topic.to_string(),
+		index,
+		ticks,
+		started: false,
+	}
+}
+
+impl<E: 'static + From<BaseError>, B: TEventBus<E>> TEventBus<E> for SimulatedBus<E, B> {
+	fn event_handler(&self) -> &'static TEventHandler<E> {
+		self.event_handler.get_or_init(|| {
+			let mut map: TEventHandler<E> = HandlerMapper::new();
+			for (topic, handlers) in self.inner.event_handler().iter() {
+				let handlers = match handlers {
+					EventHandlers::Sync(handlers) => EventHandlers::Sync(handlers.iter().enumerate().map(|(i, handler)| self.simulate_handler(topic, i, handler)).collect()),
+					EventHandlers::Async(handlers) => EventHandlers::Async(handlers.iter().enumerate().map(|(i, handler)| self.simulate_handler(topic, i, handler)).collect()),
+				};
+				map.insert(topic.clone(), handlers);
+			}
+			Box::leak(Box::new(map))
+		})
+	}
+
+	fn joined_event_handler(&self) -> Option<&'static TJoinedEventHandler> {
+		self.inner.joined_event_handler()
+	}
+}
+
+impl<R, E, C, B> TMessageBus<R, E, C> for SimulatedBus<E, B>
+where
+	BaseError: From<E>,
+	R: ApplicationResponse,
+	E: ApplicationError + From<BaseError> + 'static,
+	C: TCommand,
+	B: TMessageBus<R, E, C>,
+{
+	fn command_handler(&self, context_manager: AtomicContextManager, cmd: C) -> impl TCommandService<R, E> {
+		SimulatedService {
+			service: self.inner.command_handler(context_manager, cmd),
+			scheduler: Arc::clone(&self.scheduler),
+			command: std::any::type_name::<C>().rsplit("::").next().unwrap_or_default().to_string(),
+		}
+	}
+}
+
+struct SimulatedService<S> {
+	service: S,
+	scheduler: Arc<Scheduler>,
+	command: String,
+}
+
+impl<R, E, S: TCommandService<R, E>> TCommandService<R, E> for SimulatedService<S> {
+	async fn execute(self) -> Result<R, E> {
+		schedule(&self.scheduler, &self.command, 0, self.service.execute()).await
+	}
+}
+
+/// Run check once per seed, panicking with the first seed it panics on.
+/// When [SIMULATION_SEED] is set, only that seed is run so that the failure can be replayed.
+pub async fn fuzz(seeds: impl IntoIterator<Item = u64>, check: impl AsyncFn(u64)) {
+	let seeds: Vec<u64> = match std::env::var(SIMULATION_SEED) {
+		Ok(seed) => vec![seed.parse().expect("RUVA_SIMULATION_SEED Must Be u64")],
+		Err(_) => seeds.into_iter().collect(),
+	};
+	for seed in seeds {
+		if let Err(payload) = AssertUnwindSafe(check(seed)).catch_unwind().await {
+			let message = payload
+				.downcast_ref::<&str>()
+				.map(|msg| msg.to_string())
+				.or_else(|| payload.downcast_ref::<String>().cloned())
+				.unwrap_or_default();
+			panic!("Simulation failed with seed {seed}: {message}\nReplay with {SIMULATION_SEED}={seed}");
+		}
+	}
+}
@@ -48,7 +48,6 @@ use crate::prelude::BaseError;
 /// Template for Unit of Work
 /// Concrete implementation must implement `_commit` method
 /// If you want to add hooks on events, you can implement `process_internal_events` and `process_external_events`
-
 pub trait TUnitOfWork: Send + Sync {
 	fn begin(&mut self) -> impl std::future::Future<Output = Result<(), BaseError>> + Send;
 
@@ -68,6 +67,11 @@ pub trait TUnitOfWork: Send + Sync {
 
 	fn close(&mut self) -> impl std::future::Future<Output = ()> + Send;
 
+	/// Roll back nested units of work left open, for example by early return, so that what follows applies to the outermost one.
+	fn rollback_nested(&mut self) -> impl std::future::Future<Output = Result<(), BaseError>> + Send {
+		async { Ok(()) }
+	}
+
 	// Hook
 	fn process_internal_events(&mut self) -> impl std::future::Future<Output = Result<(), BaseError>> + Send {
 		async { Ok(()) }
@@ -77,3 +81,97 @@ pub trait TUnitOfWork: Send + Sync {
 		async { Ok(()) }
 	}
 }
+
+/// Isolation level of transaction. When not given, default of the database is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolationLevel {
+	ReadCommitted,
+	RepeatableRead,
+	Serializable,
+}
+
+impl IsolationLevel {
+	pub fn as_sql(&self) -> &'static str {
+		match self {
+			IsolationLevel::ReadCommitted => "READ COMMITTED",
+			IsolationLevel::RepeatableRead => "REPEATABLE READ",
+			IsolationLevel::Serializable => "SERIALIZABLE",
+		}
+	}
+}
+
+/// Characteristics of transaction a command is handled in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionOptions {
+	pub isolation_level: Option<IsolationLevel>,
+	pub read_only: bool,
+}
+
+impl TransactionOptions {
+	pub fn isolation_level(mut self, isolation_level: IsolationLevel) -> Self {
+		self.isolation_level = Some(isolation_level);
+		self
+	}
+
+	pub fn read_only(mut self) -> Self {
+		self.read_only = true;
+		self
+	}
+
+	/// `SET TRANSACTION` statement to run right after transaction begins, if any of the characteristics is given.
+	pub fn to_sql(&self) -> Option<String> {
+		let mut modes = vec![];
+		if let Some(isolation_level) = self.isolation_level {
+			modes.push(format!("ISOLATION LEVEL {}", isolation_level.as_sql()));
+		}
+		if self.read_only {
+			modes.push("READ ONLY".to_string());
+		}
+		(!modes.is_empty()).then(|| format!("SET TRANSACTION {}", modes.join(", ")))
+	}
+}
+
+/// Policy on re-running command whose transaction failed on conflict with concurrent ones.
+/// Wait before each attempt doubles, starting from `backoff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+	pub max_retries: usize,
+	pub backoff: std::time::Duration,
+}
+
+impl RetryPolicy {
+	pub fn new(max_retries: usize) -> Self {
+		Self {
+			max_retries,
+			backoff: std::time::Duration::from_millis(10),
+		}
+	}
+
+	pub fn backoff(mut self, backoff: std::time::Duration) -> Self {
+		self.backoff = backoff;
+		self
+	}
+
+	/// Wait before `attempt`th retry, starting from 1.
+	pub fn delay(&self, attempt: usize) -> std::time::Duration {
+		self.backoff.saturating_mul(1 << (attempt.saturating_sub(1)).min(16) as u32)
+	}
+}
+
+#[test]
+fn test_transaction_options_to_sql() {
+	assert_eq!(TransactionOptions::default().to_sql(), None);
+	assert_eq!(TransactionOptions::default().read_only().to_sql().unwrap(), "SET TRANSACTION READ ONLY");
+	assert_eq!(
+		TransactionOptions::default().isolation_level(IsolationLevel::Serializable).read_only().to_sql().unwrap(),
+		"SET TRANSACTION ISOLATION LEVEL SERIALIZABLE, READ ONLY"
+	);
+}
+
+#[test]
+fn test_retry_delay_doubles() {
+	let policy = RetryPolicy::new(3).backoff(std::time::Duration::from_millis(5));
+	assert_eq!(policy.delay(1), std::time::Duration::from_millis(5));
+	assert_eq!(policy.delay(2), std::time::Duration::from_millis(10));
+	assert_eq!(policy.delay(3), std::time::Duration::from_millis(20));
+}
@@ -5,16 +5,22 @@ use syn::{parse::Parser, parse_macro_input, punctuated::Punctuated, token::Comma
 
 use crate::{
 	helpers::{derive_helpers::add_derive_macros, generic_helpers::add_aggregate_generic_defaults},
-	utils::{check_if_field_has_attribute, extract_field_names, locate_crate_on_derive_macro, remove_fields_based_on_field_name, skip_over_attributes, sort_macros_to_inject},
+	utils::{check_if_field_has_attribute, extract_field_names, locate_crate_on_derive_macro, remove_fields_based_on_field_name, skip_over_attributes, sort_macros_to_inject, take_attribute_options},
 };
 
 pub(crate) fn render_aggregate(input: TokenStream, attrs: TokenStream) -> TokenStream {
+	let (attrs, options) = take_attribute_options(attrs, &["table", "id"]);
 	let mut macros_to_inject = vec!["ruva::Serialize".to_string(), "Debug".to_string(), "Default".to_string()];
 	sort_macros_to_inject(&mut macros_to_inject, attrs);
 
 	let mut ast = parse_macro_input!(input as DeriveInput);
 
 	let name = ast.ident.clone();
+	let id = options[1].as_deref().unwrap_or("id");
+	let repository_quote = match &options[0] {
+		Some(table) => create_repository_quote(&ast, table, id),
+		None => quote!(),
+	};
 
 	add_aggregate_generic_defaults(&mut ast.generics);
 	add_derive_macros(&mut ast, &macros_to_inject);
@@ -23,6 +29,8 @@ pub(crate) fn render_aggregate(input: TokenStream, attrs: TokenStream) -> TokenS
 
 	let crates = locate_crate_on_derive_macro(&ast);
 
+	let next_id_quote = create_next_id_quote(&ast, id, &crates);
+
 	let adapter_quote = create_struct_adapter_quote(&ast, true);
 
 	let setters = set_entity_fields(&mut ast.data, true);
@@ -31,6 +39,9 @@ pub(crate) fn render_aggregate(input: TokenStream, attrs: TokenStream) -> TokenS
 		#ast
 		impl #impl_generics  #crates::TAggregate for #name #ty_generics #where_clause {
 			// type Identifier = #aggregate_identifier_type;
+			fn aggregate_name() -> &'static str {
+				stringify!(#name)
+			}
 
 			fn events(&self) -> &::std::collections::VecDeque<::std::sync::Arc<dyn #crates::TEvent>> {
 				&self.events
@@ -46,10 +57,13 @@ pub(crate) fn render_aggregate(input: TokenStream, attrs: TokenStream) -> TokenS
 
 		impl #impl_generics #name #ty_generics #where_clause{
 			#setters
+			#next_id_quote
 		}
 
 
 		#adapter_quote
+
+		#repository_quote
 	)
 	.into()
 }
@@ -221,6 +235,18 @@ pub fn create_struct_adapter_quote(input: &DeriveInput, for_aggregate: bool) ->
 
 	let (impl_aggregate_generics, ty_aggregate_generics, where_aggregate_clause) = input.generics.split_for_impl();
 
+	// ! Adapter is linked only to aggregate
+	let adaptable_quote = if for_aggregate {
+		let crates = locate_crate_on_derive_macro(input);
+		quote!(
+			impl #impl_aggregate_generics #crates::TAdaptable for #aggregate_name #ty_aggregate_generics #where_aggregate_clause{
+				type Adapter = #adapter_name #ty_adapter_generics;
+			}
+		)
+	} else {
+		quote!()
+	};
+
 	quote!(
 
 		#adapter_input
@@ -240,9 +266,203 @@ pub fn create_struct_adapter_quote(input: &DeriveInput, for_aggregate: bool) ->
 				}
 			}
 		}
+
+		#adaptable_quote
+	)
+}
+
+/// Constructor taking id from the id generator of the request, for aggregate whose id field is of `Id`.
+fn create_next_id_quote(input: &DeriveInput, id: &str, crates: &Ident) -> proc_macro2::TokenStream {
+	let syn::Data::Struct(DataStruct {
+		fields: syn::Fields::Named(fields), ..
+	}) = &input.data
+	else {
+		return quote!();
+	};
+	let Some(field) = fields.named.iter().find(|f| f.ident.as_ref().is_some_and(|ident| ident.to_string().trim_start_matches("r#") == id)) else {
+		return quote!();
+	};
+	// * Id can't be told from other types of the same name, so it is up to the last segment of the path
+	let Type::Path(ty) = &field.ty else {
+		return quote!();
+	};
+	if ty.path.segments.last().is_none_or(|segment| segment.ident != "Id" || !segment.arguments.is_empty()) {
+		return quote!();
+	}
+	let ident = field.ident.as_ref().unwrap();
+
+	quote!(
+		/// Aggregate with id taken from the id generator of the request, and the other fields of their default.
+		pub fn with_next_id(context: &#crates::Context) -> Self
+		where
+			Self: Default,
+		{
+			Self {
+				#ident: context.next_id(),
+				..Default::default()
+			}
+		}
+	)
+}
+
+/// `sqlx::FromRow` for the adapter, and methods that write the aggregate to `table` in the transaction of [Context],
+/// collecting its events as they do.
+fn create_repository_quote(input: &DeriveInput, table: &str, id: &str) -> proc_macro2::TokenStream {
+	if !input.generics.params.is_empty() {
+		panic!("[aggregate(table = ...)] can't be attached to generic struct");
+	}
+	let name = &input.ident;
+	let adapter_name = Ident::new(&(name.to_string() + "Adapter"), proc_macro2::Span::call_site());
+	let crates = locate_crate_on_derive_macro(input);
+
+	let syn::Data::Struct(DataStruct {
+		fields: syn::Fields::Named(fields), ..
+	}) = &input.data
+	else {
+		panic!("[aggregate] can be attached only to struct")
+	};
+	// * Columns are named after the fields stored in adapter
+	let fields: Vec<&Field> = fields.named.iter().filter(|f| check_if_field_has_attribute(f, "adapter_ignore").is_none()).collect();
+	let idents: Vec<&Ident> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+	let columns: Vec<String> = idents.iter().map(|ident| ident.to_string().trim_start_matches("r#").to_string()).collect();
+
+	let Some(id_idx) = columns.iter().position(|column| column == id) else {
+		panic!("[aggregate(table = ...)] requires `{}` field as primary key", id);
+	};
+	let id_ident = idents[id_idx];
+	let id_ty = &fields[id_idx].ty;
+	let values: Vec<_> = idents.iter().filter(|ident| **ident != id_ident).collect();
+
+	// * Identifiers are quoted so that keywords such as `order` and mixed case names can be used as they are
+	let table_sql = quote_table(table);
+	let id_sql = quote_identifier(id);
+	let column_sqls = columns.iter().map(|column| quote_identifier(column)).collect::<Vec<_>>();
+	let other_sqls = column_sqls.iter().filter(|column| **column != id_sql).collect::<Vec<_>>();
+
+	let placeholders = (1..=columns.len()).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", ");
+	let insert_sql = format!("INSERT INTO {} ({}) VALUES ({})", table_sql, column_sqls.join(", "), placeholders);
+	// * Aggregate of id alone has nothing to update, so update only tells whether the row exists
+	let update_sql = match other_sqls.is_empty() {
+		true => format!("UPDATE {} SET {} = {} WHERE {} = $1", table_sql, id_sql, id_sql, id_sql),
+		false => format!(
+			"UPDATE {} SET {} WHERE {} = $1",
+			table_sql,
+			other_sqls.iter().enumerate().map(|(i, column)| format!("{} = ${}", column, i + 2)).collect::<Vec<_>>().join(", "),
+			id_sql
+		),
+	};
+	let upsert_sql = match other_sqls.is_empty() {
+		true => format!("{} ON CONFLICT ({}) DO NOTHING", insert_sql, id_sql),
+		false => format!(
+			"{} ON CONFLICT ({}) DO UPDATE SET {}",
+			insert_sql,
+			id_sql,
+			other_sqls.iter().map(|column| format!("{} = EXCLUDED.{}", column, column)).collect::<Vec<_>>().join(", ")
+		),
+	};
+	let select_sql = format!("SELECT {} FROM {} WHERE {} = $1", column_sqls.join(", "), table_sql, id_sql);
+	let delete_sql = format!("DELETE FROM {} WHERE {} = $1", table_sql, id_sql);
+
+	quote!(
+		impl<'r> #crates::sqlx::FromRow<'r, #crates::sqlx::postgres::PgRow> for #adapter_name {
+			fn from_row(row: &'r #crates::sqlx::postgres::PgRow) -> Result<Self, #crates::sqlx::Error> {
+				use #crates::sqlx::Row;
+				Ok(Self {
+					#(#idents: row.try_get(#columns)?,)*
+				})
+			}
+		}
+
+		impl #name {
+			#[doc = concat!("Insert into `", #table, "` and collect events of the aggregate.")]
+			pub async fn insert(&mut self, context: &mut #crates::Context) -> Result<(), #crates::BaseError> {
+				#crates::sqlx::query(#insert_sql)
+					#(.bind(&self.#idents))*
+					.execute(context.transaction())
+					.await?;
+				self.is_existing = true;
+				self.is_updated = false;
+				context.event_hook(self);
+				Ok(())
+			}
+
+			#[doc = concat!("Update the row of `", #table, "` and collect events of the aggregate. `BaseError::NotFound` is returned if there is no row to update.")]
+			pub async fn update(&mut self, context: &mut #crates::Context) -> Result<(), #crates::BaseError> {
+				let updated = #crates::sqlx::query(#update_sql)
+					.bind(&self.#id_ident)
+					#(.bind(&self.#values))*
+					.execute(context.transaction())
+					.await?
+					.rows_affected();
+				if updated == 0 {
+					return Err(#crates::BaseError::NotFound);
+				}
+				self.is_updated = false;
+				context.event_hook(self);
+				Ok(())
+			}
+
+			#[doc = concat!("Insert into `", #table, "`, or update the row if it exists, and collect events of the aggregate.")]
+			pub async fn upsert(&mut self, context: &mut #crates::Context) -> Result<(), #crates::BaseError> {
+				#crates::sqlx::query(#upsert_sql)
+					#(.bind(&self.#idents))*
+					.execute(context.transaction())
+					.await?;
+				self.is_existing = true;
+				self.is_updated = false;
+				context.event_hook(self);
+				Ok(())
+			}
+
+			#[doc = concat!("Load the aggregate from `", #table, "`.")]
+			pub async fn find_by_id(context: &mut #crates::Context, id: &#id_ty) -> Result<Option<Self>, #crates::BaseError> {
+				let adapter = #crates::sqlx::query_as::<_, #adapter_name>(#select_sql).bind(id).fetch_optional(context.transaction()).await?;
+				Ok(adapter.map(Self::from))
+			}
+
+			#[doc = concat!("Delete the row from `", #table, "` and collect events of the aggregate. `BaseError::NotFound` is returned if there is no row to delete.")]
+			pub async fn delete(&mut self, context: &mut #crates::Context) -> Result<(), #crates::BaseError> {
+				let deleted = #crates::sqlx::query(#delete_sql).bind(&self.#id_ident).execute(context.transaction()).await?.rows_affected();
+				if deleted == 0 {
+					return Err(#crates::BaseError::NotFound);
+				}
+				self.is_existing = false;
+				context.event_hook(self);
+				Ok(())
+			}
+		}
 	)
 }
 
+/// Identifier quoted for SQL, with quotes in it doubled.
+fn quote_identifier(identifier: &str) -> String {
+	format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Table name quoted for SQL. Schema-qualified name is quoted part by part, and parts given quoted already are kept as they are.
+fn quote_table(table: &str) -> String {
+	// * Dots within quoted part belong to the identifier
+	let mut parts = vec![String::new()];
+	let mut quoted = false;
+	for c in table.chars() {
+		match c {
+			'.' if !quoted => parts.push(String::new()),
+			_ => {
+				quoted ^= c == '"';
+				parts.last_mut().unwrap().push(c);
+			}
+		}
+	}
+	parts
+		.iter()
+		.map(|part| match part.len() > 1 && part.starts_with('"') && part.ends_with('"') {
+			true => part.to_string(),
+			false => quote_identifier(part),
+		})
+		.collect::<Vec<_>>()
+		.join(".")
+}
+
 fn try_remove_generic_type(generics: &mut Generics, ty: Type) {
 	// find the generic type and remove it from the generics and from where clause
 	let mut removed_generic = vec![];
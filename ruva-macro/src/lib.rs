@@ -15,7 +15,7 @@ mod message_handler;
 mod result;
 mod utils;
 
-#[proc_macro_derive(TEvent, attributes(internally_notifiable, externally_notifiable, identifier))]
+#[proc_macro_derive(TEvent, attributes(internally_notifiable, externally_notifiable, identifier, partition_key, codec))]
 pub fn message_derive(attr: TokenStream) -> TokenStream {
 	let mut ast: DeriveInput = syn::parse(attr.clone()).unwrap();
 	let externally_notifiable_event_req = extract_externally_notifiable_event_req(&mut ast);
@@ -151,6 +151,39 @@ pub fn message_derive(attr: TokenStream) -> TokenStream {
 /// assert_eq!(my_int32_struct.do_something_with_i32(), i32::default());
 ///
 /// ```
+///
+/// ## Id
+/// Aggregate whose `id` field, or the one given with `id`, is of `Id` gets `with_next_id`, which creates it
+/// with id taken from the id generator of the request and the other fields of their default.
+/// ```rust,no_run
+/// #[aggregate]
+/// pub struct Shipment {
+///     id: Id,
+/// }
+///
+/// let shipment = Shipment::with_next_id(context);
+/// ```
+///
+/// ## Repository
+/// With `table`, `sqlx::FromRow` is implemented for the adapter and `insert`, `update`, `upsert`, `find_by_id` and `delete`
+/// are generated for the aggregate. Columns are named after the fields of the adapter, and `id` field is the primary key unless other one is given with `id`.
+/// Table and column names are quoted, so they are taken as they are written, case included; schema-qualified table is given as `schema.table`.
+/// `update` of aggregate with no field other than the primary key only checks the row exists.
+/// They run on `Context::transaction()` and call `Context::event_hook`, so events raised on the aggregate are collected as it is written.
+/// `sqlx-postgres` feature is required.
+/// ```rust,no_run
+/// #[aggregate(table = "orders")]
+/// pub struct Order {
+///     id: i64,
+///     amount: i64,
+/// }
+///
+/// async fn amend_order(cmd: AmendOrder, context: &mut Context) -> Result<(), BaseError> {
+///     let mut order = Order::find_by_id(context, &cmd.id).await?.ok_or(BaseError::NotFound)?;
+///     order.amend(cmd.amount);
+///     order.update(context).await
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn aggregate(attrs: TokenStream, input: TokenStream) -> TokenStream {
 	domain::render_aggregate(input, attrs)
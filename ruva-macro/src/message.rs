@@ -9,15 +9,30 @@ pub(crate) fn render_message_token(ast: &DeriveInput, visibilities: Vec<TokenStr
 
 	let (metadata_generator, impl_assertion) = externally_notifiable_event_req.unwrap_or_else(|| (TokenStream::new(), TokenStream::new()));
 
+	let codec = ast.attrs.iter().find(|attr| attr.path().is_ident("codec")).map(|attr| {
+		let codec = attr.parse_args::<syn::Path>().expect("#[codec(...)] expects path to codec. Example: #[codec(ruva::MessagePackCodec)]");
+		quote!(
+			fn codec(&self) -> ::std::option::Option<&'static dyn #crates::TCodec> {
+				::std::option::Option::Some(&#codec)
+			}
+		)
+	});
+
 	quote! {
 		impl #crates::TEvent for #name {
 
 			#metadata_generator
 
+			#codec
+
 			fn state(&self) -> ::std::string::String {
 				serde_json::to_string(&self).expect("Failed to serialize")
 			}
 
+			fn payload(&self) -> ::std::option::Option<&dyn #crates::erased_serde::Serialize> {
+				::std::option::Option::Some(self)
+			}
+
 			#(#visibilities)*
 		}
 		impl #name{
@@ -125,6 +140,20 @@ pub(crate) fn generate_event_metadata(ast: &DeriveInput, aggregate_metadata: Str
 
 			let ident = identifier.first().unwrap().ident.clone().unwrap().clone();
 
+			// * Partition key defaults to aggregate id unless a field is annotated with `partition_key`
+			let partition_key = named.iter().filter(|f| get_attributes(f).into_iter().any(|ident| ident == *"partition_key")).collect::<Vec<_>>();
+			if partition_key.len() > 1 {
+				panic!("Only One partition_key Can Be Given To TEvent!")
+			}
+			let partition_key = partition_key.first().map(|f| {
+				let partition_key = f.ident.clone().unwrap();
+				quote!(
+					fn partition_key(&self) -> ::std::string::String {
+						self.#partition_key.to_string()
+					}
+				)
+			});
+
 			quote!(
 				fn metadata(&self) -> #crates::EventMetadata {
 					#crates::EventMetadata{
@@ -133,6 +162,7 @@ pub(crate) fn generate_event_metadata(ast: &DeriveInput, aggregate_metadata: Str
 					topic: stringify!(#name).into()
 				}
 			}
+			#partition_key
 			)
 		}
 		_ => panic!("Only Struct Allowed!"),
@@ -99,6 +99,11 @@ pub(crate) fn render_error_token(ast: &DeriveInput) -> TokenStream {
 					#crates::BaseError::StopSentinel => Self::#stop_sentinel,
 					#crates::BaseError::StopSentinelWithEvent(event) => Self::#stop_sentinel_with_event(event),
 					#crates::BaseError::DatabaseError(error) => Self::#database_error(error),
+					// * Error of joined handler is returned as it is when it is of this type
+					#crates::BaseError::JoinedHandlerFailed(error) => match error.take::<Self>() {
+						Some(error) => error,
+						None => Self::BaseError(#crates::BaseError::JoinedHandlerFailed(error)),
+					},
 					err => Self::BaseError(err),
 				}
 			}
@@ -106,10 +111,12 @@ pub(crate) fn render_error_token(ast: &DeriveInput) -> TokenStream {
 
 		impl ::std::convert::From<#name> for #crates::BaseError {
 			fn from(value: #name) -> Self {
+				#[allow(unreachable_patterns)]
 				let data = match value {
 					#name::#stop_sentinel => #crates::BaseError::StopSentinel,
 					#name::#stop_sentinel_with_event(event) => #crates::BaseError::StopSentinelWithEvent(event),
 					#name::#database_error(error) => #crates::BaseError::DatabaseError(error),
+					#name::BaseError(error) => error,
 					// _ => #crates::BaseError::ServiceError(::std::boxed::Box::new(value)),
 					_=> #crates::BaseError::ServiceError,
 				};
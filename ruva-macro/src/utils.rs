@@ -1,5 +1,6 @@
+use quote::ToTokens;
 use std::borrow::Borrow;
-use syn::{parse_quote, punctuated::Punctuated, token::Comma, DataEnum, DeriveInput, Field, FieldsNamed, Ident, Meta, Path, Stmt, Type, Variant};
+use syn::{parse::Parser, parse_quote, punctuated::Punctuated, token::Comma, DataEnum, DeriveInput, Field, FieldsNamed, Ident, Meta, Path, Stmt, Type, Variant};
 
 pub(crate) fn locate_crate_on_derive_macro(ast: &DeriveInput) -> Ident {
 	let crates = ast.attrs.iter().find(|x| x.path().is_ident("crates"));
@@ -159,3 +160,26 @@ pub(crate) fn strip_generic_constraints(generics: &str) -> String {
 	// Join them back with commas and wrap in angle brackets
 	format!("<{}>", stripped_params.join(", "))
 }
+
+/// Take `key = "value"` options out of attribute arguments, leaving the rest as macros to derive.
+pub(crate) fn take_attribute_options(attrs: proc_macro::TokenStream, keys: &[&str]) -> (proc_macro::TokenStream, Vec<Option<String>>) {
+	let args = Punctuated::<Meta, Comma>::parse_terminated.parse(attrs).expect("Invalid attribute arguments!");
+	let mut options = vec![None; keys.len()];
+	let mut rest: Punctuated<Meta, Comma> = Punctuated::new();
+	for arg in args {
+		match arg {
+			Meta::NameValue(name_value) => {
+				let key = name_value.path.get_ident().map(|ident| ident.to_string()).unwrap_or_default();
+				let Some(idx) = keys.iter().position(|k| *k == key) else {
+					panic!("Unknown option {}! Expected one of {:?}", key, keys);
+				};
+				let syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(value), .. }) = name_value.value else {
+					panic!("Option {} expects string literal!", key);
+				};
+				options[idx] = Some(value.value());
+			}
+			arg => rest.push(arg),
+		}
+	}
+	(rest.into_token_stream().into(), options)
+}
@@ -59,7 +59,7 @@
 //! Note that use of `internally_notifiable`(or `externally_notifiable`) and `identifier` are MUST.
 //!
 //! * `internally_notifiable` is marker to let the system know that the event should be handled
-//!    within the application
+//!   within the application
 //! * `externally_notifiable` is to leave `OutBox`.
 //! * `identifier` is to record aggregate id.
 //!
@@ -218,7 +218,10 @@
 
 pub extern crate static_assertions;
 
+pub use ruva_core::__register_event_handler;
 pub use ruva_core::__register_uow_services_internal;
+pub use ruva_core::__transaction_options;
+pub use ruva_core::__uow_command_service;
 pub use ruva_core::error;
 pub use ruva_core::init_event_handler;
 pub use ruva_core::make_conversion;
@@ -226,5 +229,7 @@ pub use ruva_core::make_smart_pointer;
 pub use ruva_core::prelude::*;
 pub use ruva_core::prepare_bulk_operation;
 pub use ruva_core::register_uow_services;
+#[cfg(feature = "testing")]
+pub use ruva_core::testing;
 
 pub use ruva_macro::{aggregate, entity, event_hook, into_command, ApplicationError, ApplicationResponse, TConstruct, TEvent};
@@ -133,3 +133,13 @@ fn test_generic_aggregate() {
 	assert_eq!(my_struct.age, 2);
 	assert!(my_struct.sub_type.is_empty());
 }
+
+#[test]
+fn test_aggregate_name() {
+	#[aggregate(Debug)]
+	pub struct OrderAggregate {
+		id: i64,
+	}
+
+	assert_eq!(OrderAggregate::aggregate_name(), "OrderAggregate");
+}
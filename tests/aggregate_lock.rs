@@ -0,0 +1,106 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::Arc;
+
+#[aggregate(Debug)]
+struct Account {
+	id: i64,
+}
+
+#[aggregate(Debug)]
+struct Ledger {
+	id: String,
+}
+
+fn context(pool: &sqlx::PgPool) -> Context {
+	Context::new(Arc::new(ContextManager::new(Arc::new(pool.clone()))))
+}
+
+#[tokio::test]
+async fn test_try_lock_fails_while_other_transaction_holds_lock() {
+	let pool = common::connect().await;
+	let id = SnowFlake::generate();
+
+	let mut holder = context(&pool);
+	holder.begin().await.unwrap();
+	holder.lock_aggregate::<Account>(id).await.unwrap();
+
+	let mut contender = context(&pool);
+	contender.begin().await.unwrap();
+	let res = contender.try_lock_aggregate::<Account>(id).await;
+	assert!(matches!(
+		res,
+		Err(BaseError::AggregateLocked { aggregate_name, aggregate_id }) if aggregate_name == "Account" && aggregate_id == id.to_string()
+	));
+
+	// * Lock is released when the transaction ends
+	holder.commit().await.unwrap();
+	contender.try_lock_aggregate::<Account>(id).await.unwrap();
+	contender.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lock_is_keyed_by_aggregate_name_and_id() {
+	let pool = common::connect().await;
+	let id = SnowFlake::generate().to_string();
+
+	let mut holder = context(&pool);
+	holder.begin().await.unwrap();
+	holder.lock_aggregate::<Ledger>(id.as_str()).await.unwrap();
+
+	let mut contender = context(&pool);
+	contender.begin().await.unwrap();
+	assert!(contender.try_lock_aggregate::<Ledger>(id.as_str()).await.is_err());
+	// * The same id of other aggregate is not locked
+	contender.try_lock_aggregate::<Account>(id.as_str()).await.unwrap();
+
+	holder.rollback().await.unwrap();
+	contender.try_lock_aggregate::<Ledger>(id.as_str()).await.unwrap();
+	contender.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_snowflake_lock_is_namespaced_by_aggregate_name() {
+	let pool = common::connect().await;
+	let id = SnowFlake::generate();
+
+	let mut holder = context(&pool);
+	holder.begin().await.unwrap();
+	holder.lock_aggregate::<Account>(id).await.unwrap();
+
+	let mut contender = context(&pool);
+	contender.begin().await.unwrap();
+	// * The same value given as i64 locks the same key
+	assert!(contender.try_lock_aggregate::<Account>(*id).await.is_err());
+	contender.try_lock_aggregate::<Ledger>(id).await.unwrap();
+
+	holder.rollback().await.unwrap();
+	contender.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lock_waits_until_other_transaction_ends() {
+	let pool = common::connect().await;
+	let id = SnowFlake::generate();
+
+	let mut holder = context(&pool);
+	holder.begin().await.unwrap();
+	holder.lock_aggregate::<Account>(id).await.unwrap();
+
+	let waiter = tokio::spawn({
+		let pool = pool.clone();
+		async move {
+			let mut waiter = context(&pool);
+			waiter.begin().await.unwrap();
+			waiter.lock_aggregate::<Account>(id).await.unwrap();
+			waiter.rollback().await.unwrap();
+		}
+	});
+	tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+	assert!(!waiter.is_finished());
+
+	holder.rollback().await.unwrap();
+	tokio::time::timeout(std::time::Duration::from_secs(5), waiter).await.unwrap().unwrap();
+}
@@ -0,0 +1,222 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::Arc;
+
+#[aggregate(Debug, Clone, table = "ruva_test_orders")]
+struct Order {
+	id: i64,
+	customer: String,
+	amount: i64,
+	#[adapter_ignore]
+	draft_note: String,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Order)]
+struct OrderChanged {
+	#[identifier]
+	id: i64,
+	amount: i64,
+}
+
+impl Order {
+	fn change(&mut self, amount: i64) {
+		self.amount = amount;
+		self.raise_event(OrderChanged { id: self.id, amount }.to_message());
+	}
+}
+
+#[aggregate(Debug, table = "ruva_test_tickets", id = "code")]
+struct Ticket {
+	code: String,
+	kind: String,
+}
+
+// * Names that must be quoted: mixed case table and keyword columns
+#[aggregate(Debug, table = "public.RuvaTestGroups", id = "user")]
+struct Membership {
+	user: String,
+	order: i64,
+}
+
+// * Schema whose name has a dot in it
+#[aggregate(Debug, table = "\"ruva.test\".RuvaTestLabels")]
+struct Label {
+	id: String,
+}
+
+#[aggregate(Debug, table = "ruva_test_tags")]
+struct Tag {
+	id: String,
+}
+
+async fn prepare(pool: &sqlx::PgPool) {
+	common::execute_ddl(
+		pool,
+		r#"
+		CREATE TABLE IF NOT EXISTS ruva_test_orders (id BIGINT PRIMARY KEY, customer TEXT NOT NULL, amount BIGINT NOT NULL);
+		CREATE TABLE IF NOT EXISTS ruva_test_tickets (code TEXT PRIMARY KEY, kind TEXT NOT NULL);
+		CREATE TABLE IF NOT EXISTS "RuvaTestGroups" ("user" TEXT PRIMARY KEY, "order" BIGINT NOT NULL);
+		CREATE TABLE IF NOT EXISTS ruva_test_tags (id TEXT PRIMARY KEY);
+		CREATE SCHEMA IF NOT EXISTS "ruva.test";
+		CREATE TABLE IF NOT EXISTS "ruva.test"."RuvaTestLabels" (id TEXT PRIMARY KEY);
+		"#,
+	)
+	.await;
+}
+
+async fn begin(pool: &sqlx::PgPool) -> Context {
+	let mut context = Context::new(Arc::new(ContextManager::new(Arc::new(pool.clone()))));
+	context.begin().await.unwrap();
+	context
+}
+
+async fn outbox_amounts(pool: &sqlx::PgPool, id: i64) -> Vec<i64> {
+	sqlx::query_scalar("SELECT (convert_from(state, 'UTF8')::JSONB->>'amount')::BIGINT FROM service_outbox WHERE aggregate_name = 'Order' AND aggregate_id = $1 ORDER BY id")
+		.bind(id.to_string())
+		.fetch_all(pool)
+		.await
+		.unwrap()
+}
+
+#[tokio::test]
+async fn test_generated_repository_writes_aggregate_and_collects_events() {
+	let pool = common::connect().await;
+	prepare(&pool).await;
+	let id = *SnowFlake::generate();
+
+	// * Events raised before each write are saved as outboxes on commit
+	let mut context = begin(&pool).await;
+	let mut order = Order {
+		id,
+		customer: "migo".into(),
+		draft_note: "not stored".into(),
+		..Default::default()
+	};
+	order.change(10);
+	order.insert(&mut context).await.unwrap();
+	assert!(order.is_existing);
+	assert!(order.events.is_empty());
+	context.commit().await.unwrap();
+
+	let mut context = begin(&pool).await;
+	let mut order = Order::find_by_id(&mut context, &id).await.unwrap().unwrap();
+	assert_eq!((order.customer.as_str(), order.amount, order.draft_note.as_str()), ("migo", 10, ""));
+	assert!(order.is_existing);
+	order.change(20);
+	order.update(&mut context).await.unwrap();
+	context.commit().await.unwrap();
+	assert_eq!(outbox_amounts(&pool, id).await, vec![10, 20]);
+
+	// * Upsert inserts new row and updates existing one
+	let mut context = begin(&pool).await;
+	let mut order = Order { id, amount: 30, ..order.clone() };
+	order.upsert(&mut context).await.unwrap();
+	let mut other = Order {
+		id: *SnowFlake::generate(),
+		customer: "other".into(),
+		..Default::default()
+	};
+	other.upsert(&mut context).await.unwrap();
+	assert_eq!(Order::find_by_id(&mut context, &id).await.unwrap().unwrap().amount, 30);
+	assert_eq!(Order::find_by_id(&mut context, &other.id).await.unwrap().unwrap().customer, "other");
+
+	order.delete(&mut context).await.unwrap();
+	assert!(Order::find_by_id(&mut context, &id).await.unwrap().is_none());
+	assert!(matches!(order.update(&mut context).await, Err(BaseError::NotFound)));
+	assert!(matches!(order.delete(&mut context).await, Err(BaseError::NotFound)));
+	context.commit().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_generated_repository_rolls_back_with_transaction() {
+	let pool = common::connect().await;
+	prepare(&pool).await;
+	let id = *SnowFlake::generate();
+
+	let mut context = begin(&pool).await;
+	let mut order = Order { id, ..Default::default() };
+	order.change(10);
+	order.insert(&mut context).await.unwrap();
+	context.rollback().await.unwrap();
+
+	let mut context = begin(&pool).await;
+	assert!(Order::find_by_id(&mut context, &id).await.unwrap().is_none());
+	context.rollback().await.unwrap();
+	assert!(outbox_amounts(&pool, id).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_generated_repository_with_custom_primary_key() {
+	let pool = common::connect().await;
+	prepare(&pool).await;
+	let code = SnowFlake::generate().to_string();
+
+	let mut context = begin(&pool).await;
+	let mut ticket = Ticket {
+		code: code.clone(),
+		kind: "vip".into(),
+		..Default::default()
+	};
+	ticket.insert(&mut context).await.unwrap();
+	ticket.kind = "standard".into();
+	ticket.update(&mut context).await.unwrap();
+	assert_eq!(Ticket::find_by_id(&mut context, &code).await.unwrap().unwrap().kind, "standard");
+	context.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_generated_repository_quotes_identifiers() {
+	let pool = common::connect().await;
+	prepare(&pool).await;
+	let user = SnowFlake::generate().to_string();
+
+	let mut context = begin(&pool).await;
+	let mut membership = Membership {
+		user: user.clone(),
+		order: 1,
+		..Default::default()
+	};
+	membership.insert(&mut context).await.unwrap();
+	membership.order = 2;
+	membership.update(&mut context).await.unwrap();
+	membership.upsert(&mut context).await.unwrap();
+	assert_eq!(Membership::find_by_id(&mut context, &user).await.unwrap().unwrap().order, 2);
+	membership.delete(&mut context).await.unwrap();
+	context.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_generated_repository_of_primary_key_alone() {
+	let pool = common::connect().await;
+	prepare(&pool).await;
+	let id = SnowFlake::generate().to_string();
+
+	// * Update has nothing to write but tells whether the row exists
+	let mut context = begin(&pool).await;
+	let mut tag = Tag { id: id.clone(), ..Default::default() };
+	assert!(matches!(tag.update(&mut context).await, Err(BaseError::NotFound)));
+	tag.insert(&mut context).await.unwrap();
+	tag.update(&mut context).await.unwrap();
+	tag.upsert(&mut context).await.unwrap();
+	assert!(Tag::find_by_id(&mut context, &id).await.unwrap().is_some());
+	context.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_generated_repository_of_schema_qualified_table() {
+	let pool = common::connect().await;
+	prepare(&pool).await;
+	let id = SnowFlake::generate().to_string();
+
+	let mut context = begin(&pool).await;
+	let mut label = Label { id: id.clone(), ..Default::default() };
+	label.insert(&mut context).await.unwrap();
+	label.upsert(&mut context).await.unwrap();
+	assert!(Label::find_by_id(&mut context, &id).await.unwrap().is_some());
+	label.delete(&mut context).await.unwrap();
+	assert!(Label::find_by_id(&mut context, &id).await.unwrap().is_none());
+	context.rollback().await.unwrap();
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Serialize)]
+struct Parcel {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TEvent)]
+#[externally_notifiable(Parcel)]
+struct ParcelLabeled {
+	#[identifier]
+	id: i64,
+	labeled_at: i64,
+}
+
+#[derive(Debug)]
+struct LabelParcel {
+	id: i64,
+}
+impl TCommand for LabelParcel {}
+
+async fn label_parcel(cmd: LabelParcel, context: &mut Context) -> Result<(), TestError> {
+	let labeled_at = context.now().timestamp();
+	context.set_current_events(vec![ParcelLabeled { id: cmd.id, labeled_at }.to_message()].into());
+	Ok(())
+}
+
+init_event_handler!(TestError, |_| (),);
+register_uow_services!((), TestError, LabelParcel => label_parcel);
+
+#[tokio::test]
+async fn test_outbox_takes_id_and_creation_time_from_context() {
+	let pool = common::connect().await;
+	let id = *SnowFlake::generate();
+	let now = SystemClock.now() - chrono::Duration::days(3);
+	let first_id = *SnowFlake::generate();
+
+	MessageBus
+		.execute_and_wait_with_context(
+			LabelParcel { id },
+			ContextManager::new(Arc::new(pool.clone()))
+				.with_clock(FixedClock::new(now))
+				.with_id_generator(SequentialIdGenerator::new(first_id)),
+		)
+		.await
+		.unwrap();
+
+	let outbox: OutBox = sqlx::query_as("SELECT * FROM service_outbox WHERE aggregate_id = $1")
+		.bind(id.to_string())
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert_eq!(outbox.id, Id::Int(first_id));
+	// * Postgres keeps microseconds
+	assert_eq!(outbox.create_dt.timestamp_micros(), now.timestamp_micros());
+	assert_eq!(outbox.decode::<serde_json::Value>().unwrap()["labeled_at"], now.timestamp());
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Serialize)]
+struct Shipment {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TEvent)]
+#[externally_notifiable(Shipment)]
+struct ShipmentCreated {
+	#[identifier]
+	id: i64,
+	items: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TEvent)]
+#[externally_notifiable(Shipment)]
+#[codec(ruva::MessagePackCodec)]
+struct ShipmentDispatched {
+	#[identifier]
+	id: i64,
+	carrier: String,
+}
+
+#[derive(Debug)]
+struct CreateShipment {
+	id: i64,
+}
+impl TCommand for CreateShipment {}
+
+async fn create_shipment(cmd: CreateShipment, context: &mut Context) -> Result<(), TestError> {
+	context.set_current_events(
+		vec![
+			ShipmentCreated {
+				id: cmd.id,
+				items: vec!["book".into(), "pen".into()],
+			}
+			.to_message(),
+			ShipmentDispatched { id: cmd.id, carrier: "ups".into() }.to_message(),
+		]
+		.into(),
+	);
+	Ok(())
+}
+
+init_event_handler!(TestError, |_| (),);
+register_uow_services!((), TestError, CreateShipment => create_shipment);
+
+async fn outboxes(pool: &sqlx::PgPool, id: i64) -> Vec<OutBox> {
+	sqlx::query_as("SELECT * FROM service_outbox WHERE aggregate_id = $1 ORDER BY sequence")
+		.bind(id.to_string())
+		.fetch_all(pool)
+		.await
+		.unwrap()
+}
+
+#[tokio::test]
+async fn test_payload_is_encoded_with_codec_of_event_or_bus() {
+	let pool = common::connect().await;
+
+	// * JSON by default
+	let id = *SnowFlake::generate();
+	MessageBus.execute_and_wait(CreateShipment { id }, Arc::new(pool.clone())).await.unwrap();
+	let [created, dispatched] = <[OutBox; 2]>::try_from(outboxes(&pool, id).await).unwrap();
+	assert_eq!(created.content_type, "application/json");
+	assert_eq!(
+		String::from_utf8(created.state.clone()).unwrap(),
+		r#"{"id":"#.to_string() + &id.to_string() + r#","items":["book","pen"]}"#
+	);
+	assert_eq!(
+		created.decode::<ShipmentCreated>().unwrap(),
+		ShipmentCreated {
+			id,
+			items: vec!["book".into(), "pen".into()]
+		}
+	);
+	assert_eq!(dispatched.content_type, "application/msgpack");
+	assert_eq!(dispatched.decode::<ShipmentDispatched>().unwrap(), ShipmentDispatched { id, carrier: "ups".into() });
+
+	// * Codec of bus applies to events without their own
+	let id = *SnowFlake::generate();
+	MessageBus
+		.execute_and_wait_with_context(CreateShipment { id }, ContextManager::new(Arc::new(pool.clone())).with_codec(CborCodec))
+		.await
+		.unwrap();
+	let [created, dispatched] = <[OutBox; 2]>::try_from(outboxes(&pool, id).await).unwrap();
+	assert_eq!(created.content_type, "application/cbor");
+	assert_eq!(created.decode::<ShipmentCreated>().unwrap().items, vec!["book".to_string(), "pen".to_string()]);
+	assert_eq!(dispatched.content_type, "application/msgpack");
+}
@@ -0,0 +1,143 @@
+#![allow(dead_code)]
+use ruva::sqlx::{self, PgPool};
+
+fn database_url() -> String {
+	std::env::var("DATABASE_URL").expect("DATABASE_URL must be set to run database tests!")
+}
+
+/// Connect to database given by `DATABASE_URL` and make sure schema ruva relies on is in place.
+pub async fn connect() -> PgPool {
+	let pool = PgPool::connect(&database_url()).await.expect("Connection Failed!");
+	prepare_schema(&pool).await;
+	pool
+}
+
+/// Connect to database with the given name on the server of `DATABASE_URL`, creating it if it doesn't exist.
+pub async fn connect_to_database(name: &str) -> PgPool {
+	let url = database_url();
+	let server = PgPool::connect(&url).await.expect("Connection Failed!");
+	let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+		.bind(name)
+		.fetch_one(&server)
+		.await
+		.unwrap();
+	if !exists {
+		// * Other test may have created it in the meantime
+		let _ = sqlx::raw_sql(&format!("CREATE DATABASE {name}")).execute(&server).await;
+	}
+	server.close().await;
+
+	let (base, _) = url.rsplit_once('/').expect("Invalid DATABASE_URL!");
+	let pool = PgPool::connect(&format!("{base}/{name}")).await.expect("Connection Failed!");
+	prepare_schema(&pool).await;
+	pool
+}
+
+/// Name of database no other test run shares, for tests that can't share one with concurrent runs.
+pub fn unique_database_name(prefix: &str) -> String {
+	format!("{prefix}_{}", *ruva::SnowFlake::generate())
+}
+
+/// Drop database created with [connect_to_database] once the test is done with it.
+pub async fn drop_database(pool: PgPool, name: &str) {
+	pool.close().await;
+	let server = PgPool::connect(&database_url()).await.expect("Connection Failed!");
+	sqlx::raw_sql(&format!("DROP DATABASE IF EXISTS {name} WITH (FORCE)")).execute(&server).await.unwrap();
+	server.close().await;
+}
+
+/// Connect to database no other test shares, named after the prefix, whose outbox ids, and ids referring to them, are of the given SQL type.
+/// Database is to be dropped with [drop_database] along with the returned name.
+pub async fn connect_with_id_type(prefix: &str, id_type: &str) -> (PgPool, String) {
+	let name = unique_database_name(prefix);
+	let pool = connect_to_database(&name).await;
+	execute_ddl(
+		&pool,
+		&format!(
+			r#"
+			ALTER TABLE service_outbox ALTER COLUMN id TYPE {id_type} USING NULL;
+			ALTER TABLE service_subscription_checkpoint ALTER COLUMN outbox_id TYPE {id_type} USING NULL;
+			"#
+		),
+	)
+	.await;
+	(pool, name)
+}
+
+async fn prepare_schema(pool: &PgPool) {
+	// * Tests run concurrently so schema creation is serialized with advisory lock.
+	let mut trx = pool.begin().await.unwrap();
+	sqlx::query("SELECT pg_advisory_xact_lock(7428)").execute(&mut *trx).await.unwrap();
+	sqlx::raw_sql(
+		r#"
+		CREATE TABLE IF NOT EXISTS service_outbox (
+			id BIGINT PRIMARY KEY,
+			aggregate_id TEXT NOT NULL,
+			aggregate_name TEXT NOT NULL,
+			topic TEXT NOT NULL,
+			state BYTEA NOT NULL,
+			content_type TEXT NOT NULL DEFAULT 'application/json',
+			processed BOOLEAN NOT NULL DEFAULT FALSE,
+			create_dt TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+			sequence BIGINT NOT NULL DEFAULT 0,
+			partition_key TEXT NOT NULL DEFAULT ''
+		);
+		ALTER TABLE service_outbox ADD COLUMN IF NOT EXISTS content_type TEXT NOT NULL DEFAULT 'application/json';
+		DO $$ BEGIN
+			IF (SELECT data_type FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = 'service_outbox' AND column_name = 'state') = 'text' THEN
+				ALTER TABLE service_outbox ALTER COLUMN state TYPE BYTEA USING convert_to(state, 'UTF8');
+			END IF;
+		END $$;
+		ALTER TABLE service_outbox ADD COLUMN IF NOT EXISTS sequence BIGINT NOT NULL DEFAULT 0;
+		ALTER TABLE service_outbox ADD COLUMN IF NOT EXISTS partition_key TEXT NOT NULL DEFAULT '';
+		ALTER TABLE service_outbox ADD COLUMN IF NOT EXISTS transaction_id BIGINT NOT NULL DEFAULT pg_current_xact_id()::TEXT::BIGINT;
+		CREATE INDEX IF NOT EXISTS service_outbox_stream ON service_outbox (transaction_id, id);
+		ALTER TABLE service_outbox ADD COLUMN IF NOT EXISTS partition_sequence BIGINT NOT NULL DEFAULT 0;
+		CREATE INDEX IF NOT EXISTS service_outbox_partition ON service_outbox (partition_key, partition_sequence) WHERE NOT processed;
+		CREATE TABLE IF NOT EXISTS service_outbox_partition_sequence (
+			partition_key TEXT PRIMARY KEY,
+			last_sequence BIGINT NOT NULL
+		);
+		CREATE TABLE IF NOT EXISTS service_outbox_sequence (
+			aggregate_name TEXT NOT NULL,
+			aggregate_id TEXT NOT NULL,
+			last_sequence BIGINT NOT NULL,
+			PRIMARY KEY (aggregate_name, aggregate_id)
+		);
+		CREATE TABLE IF NOT EXISTS service_aggregate_snapshot (
+			aggregate_name TEXT NOT NULL,
+			aggregate_id TEXT NOT NULL,
+			version BIGINT NOT NULL,
+			schema_version INT NOT NULL,
+			state JSONB NOT NULL,
+			create_dt TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+			PRIMARY KEY (aggregate_name, aggregate_id)
+		);
+		CREATE TABLE IF NOT EXISTS service_machine_id_lease (
+			datacenter_id INT NOT NULL,
+			machine_id INT NOT NULL,
+			holder TEXT NOT NULL,
+			expires_at TIMESTAMPTZ NOT NULL,
+			PRIMARY KEY (datacenter_id, machine_id)
+		);
+		CREATE TABLE IF NOT EXISTS service_subscription_checkpoint (
+			subscription TEXT PRIMARY KEY,
+			transaction_id BIGINT NOT NULL,
+			outbox_id BIGINT NOT NULL,
+			update_dt TIMESTAMPTZ NOT NULL DEFAULT NOW()
+		);
+		"#,
+	)
+	.execute(&mut *trx)
+	.await
+	.unwrap();
+	trx.commit().await.unwrap();
+}
+
+/// Run DDL for test specific tables under the same lock as [connect].
+pub async fn execute_ddl(pool: &PgPool, ddl: &str) {
+	let mut trx = pool.begin().await.unwrap();
+	sqlx::query("SELECT pg_advisory_xact_lock(7428)").execute(&mut *trx).await.unwrap();
+	sqlx::raw_sql(ddl).execute(&mut *trx).await.unwrap();
+	trx.commit().await.unwrap();
+}
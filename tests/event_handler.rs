@@ -0,0 +1,77 @@
+use ruva::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(std::sync::Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[internally_notifiable]
+struct ItemRegistered {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[internally_notifiable]
+struct ItemCounted {
+	id: i64,
+}
+
+static COUNTED: AtomicUsize = AtomicUsize::new(0);
+
+struct ItemEventHandler;
+impl ItemEventHandler {
+	async fn count_item(self, event: ItemRegistered, context: &mut Context) -> Result<(), TestError> {
+		context.set_current_events(vec![ItemCounted { id: event.id }.to_message()].into());
+		Ok(())
+	}
+	async fn record_count(self, _event: ItemCounted) -> Result<(), TestError> {
+		COUNTED.fetch_add(1, Ordering::SeqCst);
+		Ok(())
+	}
+}
+
+init_event_handler!(
+	TestError,
+	|_| ItemEventHandler,
+	ItemRegistered: [#[transaction(none)] count_item],
+	ItemCounted: [record_count]
+);
+
+#[derive(Debug)]
+struct RegisterItem {
+	id: i64,
+}
+impl TCommand for RegisterItem {}
+
+struct RegisterItemService(AtomicContextManager, RegisterItem);
+impl TCommandService<(), TestError> for RegisterItemService {
+	async fn execute(self) -> Result<(), TestError> {
+		let mut context = Context::new(self.0);
+		context.set_current_events(vec![ItemRegistered { id: self.1.id }.to_message()].into());
+		context.send_internally_notifiable_messages().await;
+		Ok(())
+	}
+}
+
+impl TMessageBus<(), TestError, RegisterItem> for MessageBus {
+	fn command_handler(&self, context_manager: AtomicContextManager, cmd: RegisterItem) -> impl TCommandService<(), TestError> {
+		RegisterItemService(context_manager, cmd)
+	}
+}
+
+struct NoConnection;
+impl TConnection for NoConnection {}
+
+#[tokio::test]
+async fn test_event_handler_without_transaction_propagates_raised_events() {
+	MessageBus.execute_and_wait(RegisterItem { id: 1 }, std::sync::Arc::new(NoConnection)).await.unwrap();
+
+	assert_eq!(COUNTED.load(Ordering::SeqCst), 1);
+}
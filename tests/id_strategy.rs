@@ -0,0 +1,117 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Serialize)]
+struct Shipment {
+	id: Id,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Shipment)]
+struct ShipmentDispatched {
+	#[identifier]
+	id: Id,
+}
+
+#[derive(Debug)]
+struct DispatchShipment;
+impl TCommand for DispatchShipment {}
+
+async fn dispatch_shipment(_: DispatchShipment, context: &mut Context) -> Result<(), TestError> {
+	let mut shipment = Shipment::with_next_id(context);
+	shipment.raise_event(ShipmentDispatched { id: shipment.id }.to_message());
+	context.event_hook(&mut shipment);
+	Ok(())
+}
+
+init_event_handler!(TestError, |_| (),);
+register_uow_services!((), TestError, DispatchShipment => dispatch_shipment);
+
+#[derive(Clone, Default)]
+struct RecordingSubscriber(Arc<Mutex<Vec<Id>>>);
+impl TEventSubscriber for RecordingSubscriber {
+	async fn handle(&self, outbox: &OutBox) -> Result<(), BaseError> {
+		self.0.lock().unwrap().push(outbox.id);
+		Ok(())
+	}
+}
+impl TOutBoxPublisher for RecordingSubscriber {
+	async fn publish(&self, outbox: &OutBox) -> Result<(), BaseError> {
+		self.handle(outbox).await
+	}
+}
+
+/// Dispatch shipments with ids of the generator and follow them through consumer and subscription.
+async fn dispatch_and_relay(pool: &sqlx::PgPool, id_generator: impl TIdGenerator + Clone + 'static) -> Vec<Id> {
+	let start = StreamPosition::end(pool).await.unwrap();
+	for _ in 0..3 {
+		MessageBus
+			.execute_and_wait_with_context(DispatchShipment, ContextManager::new(Arc::new(pool.clone())).with_id_generator(id_generator.clone()))
+			.await
+			.unwrap();
+	}
+
+	let outboxes: Vec<(Id, String)> = sqlx::query_as("SELECT id, aggregate_id FROM service_outbox WHERE transaction_id >= $1 ORDER BY transaction_id, id")
+		.bind(start.transaction_id)
+		.fetch_all(pool)
+		.await
+		.unwrap();
+	assert_eq!(outboxes.len(), 3);
+	// * Both outbox and the aggregate take their ids from the generator
+	assert!(outboxes.iter().all(|(id, aggregate_id)| *id != aggregate_id.parse().unwrap()));
+
+	let relayed = RecordingSubscriber::default();
+	OutBoxConsumer::new(pool.clone(), "ruva_test_id_strategy").process_pending(&relayed).await.unwrap();
+	let unprocessed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM service_outbox WHERE NOT processed").fetch_one(pool).await.unwrap();
+	assert_eq!(unprocessed, 0);
+
+	let name = format!("test-id-strategy-{}", *SnowFlake::generate());
+	let subscriber = RecordingSubscriber::default();
+	let subscription = EventSubscription::new(pool.clone(), name).start_from(start);
+	subscription.catch_up(&subscriber).await.unwrap();
+	let ids = outboxes.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+	assert_eq!(*subscriber.0.lock().unwrap(), ids);
+	assert!(relayed.0.lock().unwrap().iter().all(|id| ids.contains(id)));
+
+	// * Checkpoint holds the id in its own type
+	assert_eq!(subscription.checkpoint().await.unwrap().unwrap().outbox_id, ids.last().copied());
+	subscription.catch_up(&RecordingSubscriber::default()).await.unwrap();
+	ids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_outbox_with_uuid_v7_ids() {
+	let (pool, name) = common::connect_with_id_type("ruva_test_uuid_ids", "uuid").await;
+	let ids = dispatch_and_relay(&pool, UuidV7IdGenerator).await;
+	assert!(ids.iter().all(|id| id.as_uuid().is_some_and(|id| id.get_version_num() == 7)));
+	common::drop_database(pool, &name).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_outbox_with_ulid_ids() {
+	let (pool, name) = common::connect_with_id_type("ruva_test_ulid_ids", "text").await;
+	let ids = dispatch_and_relay(&pool, UlidIdGenerator).await;
+	assert!(ids.iter().all(|id| id.as_ulid().is_some()));
+	common::drop_database(pool, &name).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_outbox_with_snowflake_ids() {
+	let (pool, name) = common::connect_with_id_type("ruva_test_snowflake_ids", "bigint").await;
+	let ids = dispatch_and_relay(&pool, SnowFlakeIdGenerator).await;
+	assert!(ids.iter().all(|id| id.as_i64().is_some()));
+	common::drop_database(pool, &name).await;
+}
@@ -6,6 +6,7 @@ use ruva::*;
 fn test_declare_internal_event() {
 	#[aggregate]
 	#[derive(Debug, Clone, Serialize, Default)]
+	#[allow(dead_code)]
 	pub struct SomeAggregate {
 		#[adapter_ignore]
 		id: i32,
@@ -0,0 +1,71 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::time::Duration;
+
+// * Datacenter no other test uses, so that leases are not taken by them
+const DATACENTER_ID: i32 = 31;
+
+#[tokio::test]
+async fn test_machine_id_is_leased_to_one_instance_at_a_time() {
+	let pool = common::connect().await;
+	sqlx::query("DELETE FROM service_machine_id_lease WHERE datacenter_id = $1")
+		.bind(DATACENTER_ID)
+		.execute(&pool)
+		.await
+		.unwrap();
+
+	// * Instances claim different ids
+	let first = MachineIdAllocator::new(pool.clone(), DATACENTER_ID).holder("pod-a").acquire().await.unwrap();
+	let second = MachineIdAllocator::new(pool.clone(), DATACENTER_ID).holder("pod-b").acquire().await.unwrap();
+	assert_eq!(first.node(), NodeId::new(DATACENTER_ID, 0).unwrap());
+	assert_eq!(second.node(), NodeId::new(DATACENTER_ID, 1).unwrap());
+	first.renew().await.unwrap();
+
+	// * Released id is claimed again right away
+	first.release().await.unwrap();
+	let third = MachineIdAllocator::new(pool.clone(), DATACENTER_ID).holder("pod-c").acquire().await.unwrap();
+	assert_eq!(third.node().machine_id, 0);
+
+	// * Expired lease is taken over, after which its holder can't renew it
+	let expiring = MachineIdAllocator::new(pool.clone(), DATACENTER_ID)
+		.holder("pod-d")
+		.ttl(Duration::from_millis(1))
+		.acquire()
+		.await
+		.unwrap();
+	assert_eq!(expiring.node().machine_id, 2);
+	// * No other test of this file generates SnowFlake, so it can take the node of the lease
+	SnowFlake::configure(expiring.node()).unwrap();
+	assert!(SnowFlake::try_generate().is_ok());
+	tokio::time::sleep(Duration::from_millis(20)).await;
+	let taker = MachineIdAllocator::new(pool.clone(), DATACENTER_ID).holder("pod-e").acquire().await.unwrap();
+	assert_eq!(taker.node().machine_id, 2);
+	assert!(matches!(expiring.keep_alive().await, BaseError::InvalidNodeId(_)));
+
+	// * SnowFlake stops generating ids with the lost node, and release leaves lease of the new holder alone
+	assert!(matches!(SnowFlake::try_generate(), Err(BaseError::InvalidNodeId(msg)) if msg.contains("revoked")));
+	expiring.release().await.unwrap();
+	taker.renew().await.unwrap();
+
+	// * No id is left once every one is held
+	let mut leases = vec![];
+	for _ in 3..=NodeId::MAX_ID {
+		leases.push(MachineIdAllocator::new(pool.clone(), DATACENTER_ID).acquire().await.unwrap());
+	}
+	assert_eq!(leases.last().unwrap().node().machine_id, NodeId::MAX_ID);
+	let holders: i64 = sqlx::query_scalar("SELECT COUNT(DISTINCT holder) FROM service_machine_id_lease WHERE datacenter_id = $1 AND holder NOT LIKE 'pod-%'")
+		.bind(DATACENTER_ID)
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert_eq!(holders, leases.len() as i64, "default holders of the same process must differ");
+	assert!(matches!(
+		MachineIdAllocator::new(pool.clone(), DATACENTER_ID).acquire().await,
+		Err(BaseError::InvalidNodeId(msg)) if msg.contains("no machine id is free")
+	));
+
+	// * Datacenter id out of bounds is rejected before anything is claimed
+	assert!(MachineIdAllocator::new(pool.clone(), 32).acquire().await.is_err());
+}
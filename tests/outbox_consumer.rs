@@ -0,0 +1,115 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Serialize)]
+struct Parcel {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Parcel)]
+struct ParcelShipped {
+	#[identifier]
+	id: i64,
+}
+
+#[derive(Debug)]
+struct ShipParcel {
+	id: i64,
+}
+impl TCommand for ShipParcel {}
+
+async fn ship_parcel(cmd: ShipParcel, context: &mut Context) -> Result<(), TestError> {
+	context.set_current_events(vec![ParcelShipped { id: cmd.id }.to_message()].into());
+	Ok(())
+}
+
+init_event_handler!(TestError, |_| (),);
+register_uow_services!((), TestError, ShipParcel => ship_parcel);
+
+#[derive(Clone, Default)]
+struct RecordingPublisher(Arc<Mutex<Vec<String>>>);
+impl TOutBoxPublisher for RecordingPublisher {
+	async fn publish(&self, outbox: &OutBox) -> Result<(), BaseError> {
+		self.0.lock().unwrap().push(outbox.aggregate_id.clone());
+		Ok(())
+	}
+}
+
+impl RecordingPublisher {
+	async fn wait_for(&self, aggregate_id: i64, timeout: Duration) -> bool {
+		let aggregate_id = aggregate_id.to_string();
+		tokio::time::timeout(timeout, async {
+			while !self.0.lock().unwrap().contains(&aggregate_id) {
+				tokio::time::sleep(Duration::from_millis(10)).await;
+			}
+		})
+		.await
+		.is_ok()
+	}
+}
+
+// * Consumers relay any unprocessed outbox, so the scenarios run one after another not to steal outboxes from each other.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_outbox_consumer_is_woken_by_notification_and_polls_as_fallback() {
+	let pool = common::connect().await;
+	let channel = "ruva_test_outbox";
+
+	// * Woken by notification long before the next poll
+	let publisher = RecordingPublisher::default();
+	let consumer = tokio::spawn({
+		let (pool, publisher) = (pool.clone(), publisher.clone());
+		async move { OutBoxConsumer::new(pool, channel).poll_interval(Duration::from_secs(60)).run(&publisher).await }
+	});
+	tokio::time::sleep(Duration::from_millis(300)).await;
+
+	let id = *SnowFlake::generate();
+	MessageBus
+		.execute_and_wait_with_context(ShipParcel { id }, ContextManager::new(Arc::new(pool.clone())).with_outbox_channel(channel))
+		.await
+		.unwrap();
+	assert!(publisher.wait_for(id, Duration::from_secs(5)).await);
+	consumer.abort();
+
+	// * Outbox saved without notification is picked up by polling
+	let publisher = RecordingPublisher::default();
+	let consumer = tokio::spawn({
+		let (pool, publisher) = (pool.clone(), publisher.clone());
+		async move { OutBoxConsumer::new(pool, channel).poll_interval(Duration::from_millis(100)).run(&publisher).await }
+	});
+	tokio::time::sleep(Duration::from_millis(300)).await;
+
+	let id = *SnowFlake::generate();
+	MessageBus.execute_and_wait(ShipParcel { id }, Arc::new(pool.clone())).await.unwrap();
+	assert!(publisher.wait_for(id, Duration::from_secs(5)).await);
+	let processed = tokio::time::timeout(Duration::from_secs(5), async {
+		loop {
+			let processed: bool = sqlx::query_scalar("SELECT processed FROM service_outbox WHERE aggregate_id = $1")
+				.bind(id.to_string())
+				.fetch_one(&pool)
+				.await
+				.unwrap();
+			if processed {
+				break;
+			}
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+	})
+	.await;
+	assert!(processed.is_ok());
+	consumer.abort();
+}
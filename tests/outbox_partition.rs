@@ -0,0 +1,180 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc, Mutex,
+};
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Serialize)]
+struct Cart {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Cart)]
+struct ItemAdded {
+	#[identifier]
+	id: i64,
+	item: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Cart)]
+struct CartCheckedOut {
+	#[identifier]
+	id: i64,
+	#[partition_key]
+	customer: String,
+}
+
+#[derive(Debug)]
+struct AddItems {
+	items: Vec<(i64, i64)>,
+}
+impl TCommand for AddItems {}
+
+async fn add_items(cmd: AddItems, context: &mut Context) -> Result<(), TestError> {
+	context.set_current_events(cmd.items.into_iter().map(|(id, item)| ItemAdded { id, item }.to_message()).collect());
+	Ok(())
+}
+
+#[derive(Debug)]
+struct CheckOut {
+	id: i64,
+	customer: String,
+}
+impl TCommand for CheckOut {}
+
+async fn check_out(cmd: CheckOut, context: &mut Context) -> Result<(), TestError> {
+	context.set_current_events(vec![CartCheckedOut { id: cmd.id, customer: cmd.customer }.to_message()].into());
+	Ok(())
+}
+
+init_event_handler!(TestError, |_| (),);
+register_uow_services!((), TestError, AddItems => add_items, CheckOut => check_out);
+
+#[derive(Clone, Default)]
+struct FlakyPublisher {
+	published: Arc<Mutex<Vec<(String, i64)>>>,
+	failing_outbox: Arc<Mutex<Option<(String, i64)>>>,
+	failed: Arc<AtomicBool>,
+}
+
+impl TOutBoxPublisher for FlakyPublisher {
+	async fn publish(&self, outbox: &OutBox) -> Result<(), BaseError> {
+		if self.failing_outbox.lock().unwrap().as_ref() == Some(&(outbox.aggregate_id.clone(), outbox.sequence)) {
+			self.failed.store(true, Ordering::SeqCst);
+			return Err(BaseError::ServiceError);
+		}
+		self.published.lock().unwrap().push((outbox.aggregate_id.clone(), outbox.sequence));
+		Ok(())
+	}
+}
+
+impl FlakyPublisher {
+	fn published_sequences(&self, aggregate_id: i64) -> Vec<i64> {
+		let aggregate_id = aggregate_id.to_string();
+		self.published.lock().unwrap().iter().filter(|(id, _)| *id == aggregate_id).map(|(_, sequence)| *sequence).collect()
+	}
+}
+
+async fn sequences(pool: &sqlx::PgPool, aggregate_id: i64) -> Vec<(i64, String)> {
+	sqlx::query_as("SELECT sequence, convert_from(state, 'UTF8') FROM service_outbox WHERE aggregate_id = $1 ORDER BY sequence")
+		.bind(aggregate_id.to_string())
+		.fetch_all(pool)
+		.await
+		.unwrap()
+}
+
+#[tokio::test]
+async fn test_sequence_is_assigned_per_aggregate_in_order_raised() {
+	let pool = common::connect().await;
+	let (a, b) = (*SnowFlake::generate(), *SnowFlake::generate());
+
+	MessageBus.execute_and_wait(AddItems { items: vec![(a, 1), (b, 1), (a, 2)] }, Arc::new(pool.clone())).await.unwrap();
+	MessageBus.execute_and_wait(AddItems { items: vec![(a, 3)] }, Arc::new(pool.clone())).await.unwrap();
+
+	let states = |items: &[i64], id: i64| items.iter().map(|item| serde_json::json!({ "id": id, "item": item }).to_string()).collect::<Vec<_>>();
+	let a_sequences = sequences(&pool, a).await;
+	assert_eq!(a_sequences.iter().map(|(sequence, _)| *sequence).collect::<Vec<_>>(), vec![1, 2, 3]);
+	assert_eq!(a_sequences.into_iter().map(|(_, state)| state).collect::<Vec<_>>(), states(&[1, 2, 3], a));
+	assert_eq!(sequences(&pool, b).await.into_iter().map(|(sequence, _)| sequence).collect::<Vec<_>>(), vec![1]);
+}
+
+#[tokio::test]
+async fn test_partition_key_is_aggregate_id_unless_given() {
+	let pool = common::connect().await;
+	let id = *SnowFlake::generate();
+
+	MessageBus.execute_and_wait(AddItems { items: vec![(id, 1)] }, Arc::new(pool.clone())).await.unwrap();
+	MessageBus.execute_and_wait(CheckOut { id, customer: "customer-1".into() }, Arc::new(pool.clone())).await.unwrap();
+
+	let partition_keys: Vec<String> = sqlx::query_scalar("SELECT partition_key FROM service_outbox WHERE aggregate_id = $1 ORDER BY sequence")
+		.bind(id.to_string())
+		.fetch_all(&pool)
+		.await
+		.unwrap();
+	assert_eq!(partition_keys, vec![id.to_string(), "customer-1".to_string()]);
+}
+
+// * Consumer relays any unprocessed outbox, so it is the only test consuming outboxes of the common database in this file.
+#[tokio::test]
+async fn test_failed_partition_stops_while_others_go_on() {
+	let pool = common::connect().await;
+	let (a, b) = (*SnowFlake::generate(), *SnowFlake::generate());
+	MessageBus
+		.execute_and_wait(
+			AddItems {
+				items: vec![(a, 1), (a, 2), (b, 1), (a, 3), (b, 2)],
+			},
+			Arc::new(pool.clone()),
+		)
+		.await
+		.unwrap();
+
+	let publisher = FlakyPublisher::default();
+	*publisher.failing_outbox.lock().unwrap() = Some((a.to_string(), 2));
+	let consumer = OutBoxConsumer::new(pool.clone(), "ruva_test_outbox_partition").batch_size(1).parallelism(4);
+
+	assert!(consumer.process_pending(&publisher).await.is_err());
+	assert!(publisher.failed.load(Ordering::SeqCst));
+	assert_eq!(publisher.published_sequences(a), vec![1]);
+	assert_eq!(publisher.published_sequences(b), vec![1, 2]);
+
+	// * Partition resumes from the failed outbox
+	*publisher.failing_outbox.lock().unwrap() = None;
+	consumer.process_pending(&publisher).await.unwrap();
+	assert_eq!(publisher.published_sequences(a), vec![1, 2, 3]);
+	assert_eq!(publisher.published_sequences(b), vec![1, 2]);
+}
+
+#[tokio::test]
+async fn test_partition_shared_by_aggregates_is_published_in_commit_order() {
+	let name = common::unique_database_name("ruva_test_outbox_partition");
+	let pool = common::connect_to_database(&name).await;
+	let (a, b) = (*SnowFlake::generate(), *SnowFlake::generate());
+	for id in [a, b, b, a] {
+		MessageBus.execute_and_wait(CheckOut { id, customer: "customer-1".into() }, Arc::new(pool.clone())).await.unwrap();
+	}
+
+	let publisher = FlakyPublisher::default();
+	let consumer = OutBoxConsumer::new(pool.clone(), "ruva_test_outbox_partition").batch_size(3);
+	assert_eq!(consumer.process_pending(&publisher).await.unwrap(), 4);
+	assert_eq!(
+		*publisher.published.lock().unwrap(),
+		vec![(a.to_string(), 1), (b.to_string(), 1), (b.to_string(), 2), (a.to_string(), 2)]
+	);
+	common::drop_database(pool, &name).await;
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::time::Duration;
+
+async fn insert_outbox(pool: &sqlx::PgPool, age_in_days: i32, processed: bool) -> i64 {
+	let id = *SnowFlake::generate();
+	sqlx::query(
+		r#"
+		INSERT INTO service_outbox (id, aggregate_id, aggregate_name, topic, state, processed, create_dt, partition_sequence)
+		VALUES ($1, $1::TEXT, 'Retention', 'Retained', '{}', $2, NOW() - make_interval(days => $3), $3)
+		"#,
+	)
+	.bind(id)
+	.bind(processed)
+	.bind(age_in_days)
+	.execute(pool)
+	.await
+	.unwrap();
+	id
+}
+
+/// Position of outbox in the event stream and in its partition.
+async fn position(pool: &sqlx::PgPool, table: &str, id: i64) -> (i64, i64) {
+	sqlx::query_as(&format!("SELECT transaction_id, partition_sequence FROM {table} WHERE id = $1"))
+		.bind(id)
+		.fetch_one(pool)
+		.await
+		.unwrap()
+}
+
+async fn exists(pool: &sqlx::PgPool, table: &str, id: i64) -> bool {
+	sqlx::query_scalar(&format!("SELECT EXISTS (SELECT 1 FROM {table} WHERE id = $1)"))
+		.bind(id)
+		.fetch_one(pool)
+		.await
+		.unwrap()
+}
+
+// * Retention applies to the whole table, so it runs on database of its own with the scenarios one after another.
+#[tokio::test]
+async fn test_outbox_retention() {
+	let name = common::unique_database_name("ruva_test_retention");
+	let pool = common::connect_to_database(&name).await;
+	let retention = Duration::from_secs(7 * 24 * 60 * 60);
+
+	// * Only processed outboxes past retention are archived to history table and deleted
+	let expired = insert_outbox(&pool, 10, true).await;
+	let unprocessed = insert_outbox(&pool, 10, false).await;
+	let recent = insert_outbox(&pool, 1, true).await;
+	let (expired_position, unprocessed_position) = (position(&pool, "service_outbox", expired).await, position(&pool, "service_outbox", unprocessed).await);
+
+	let report = OutBoxRetention::new(pool.clone(), retention)
+		.batch_size(1)
+		.archive(OutBoxArchive::HistoryTable("test_outbox_history".into()))
+		.run()
+		.await
+		.unwrap();
+
+	assert_eq!(report.deleted, 1);
+	assert_eq!(report.archived, report.deleted);
+	assert_eq!(report.batches, report.deleted);
+	assert!(!exists(&pool, "service_outbox", expired).await);
+	assert!(exists(&pool, "test_outbox_history", expired).await);
+	assert_eq!(position(&pool, "test_outbox_history", expired).await, expired_position);
+	assert!(exists(&pool, "service_outbox", unprocessed).await);
+	assert!(exists(&pool, "service_outbox", recent).await);
+
+	// * Unprocessed ones are purged as well when asked to, archived to JSON Lines file
+	let path = std::env::temp_dir().join(format!("ruva_outbox_{}.jsonl", *SnowFlake::generate()));
+	let report = OutBoxRetention::new(pool.clone(), retention)
+		.include_unprocessed()
+		.archive(OutBoxArchive::JsonLines(path.clone()))
+		.run()
+		.await
+		.unwrap();
+
+	assert_eq!(report.deleted, 1);
+	assert!(!exists(&pool, "service_outbox", unprocessed).await);
+	assert!(exists(&pool, "service_outbox", recent).await);
+	let archived = std::fs::read_to_string(&path)
+		.unwrap()
+		.lines()
+		.map(|line| serde_json::from_str::<ArchivedOutBox>(line).unwrap())
+		.collect::<Vec<_>>();
+	std::fs::remove_file(&path).unwrap();
+	assert_eq!(archived.len() as u64, report.archived);
+	let archived = archived.iter().find(|archived| archived.outbox.id == Id::Int(unprocessed)).unwrap();
+	assert!(!archived.outbox.processed);
+	assert_eq!((archived.transaction_id, archived.partition_sequence), unprocessed_position);
+
+	// * Nothing is left to remove
+	let report = OutBoxRetention::new(pool.clone(), retention).include_unprocessed().run().await.unwrap();
+	assert_eq!(report, RetentionReport::default());
+
+	// * Outboxes from transaction of the oldest subscription checkpoint on are kept until it moves past them
+	let read = insert_outbox(&pool, 10, true).await;
+	let unread = insert_outbox(&pool, 10, true).await;
+	sqlx::query("INSERT INTO service_subscription_checkpoint (subscription, transaction_id, outbox_id) VALUES ('retention', $1, $2)")
+		.bind(position(&pool, "service_outbox", unread).await.0)
+		.bind(unread)
+		.execute(&pool)
+		.await
+		.unwrap();
+	let report = OutBoxRetention::new(pool.clone(), retention).run().await.unwrap();
+	assert_eq!(report.deleted, 1);
+	assert!(!exists(&pool, "service_outbox", read).await);
+	assert!(exists(&pool, "service_outbox", unread).await);
+
+	sqlx::query("DELETE FROM service_subscription_checkpoint").execute(&pool).await.unwrap();
+	let report = OutBoxRetention::new(pool.clone(), retention).run().await.unwrap();
+	assert_eq!(report.deleted, 1);
+	assert!(!exists(&pool, "service_outbox", unread).await);
+
+	// * Invalid setting is rejected, and outbox whose archive failed is kept
+	let invalid_table = OutBoxRetention::new(pool.clone(), retention).archive(OutBoxArchive::HistoryTable("history; DROP TABLE service_outbox".into()));
+	assert!(matches!(invalid_table.run().await, Err(BaseError::InvalidConfiguration(msg)) if msg.starts_with("Invalid Table Name")));
+	assert!(matches!(OutBoxRetention::new(pool.clone(), Duration::MAX).run().await, Err(BaseError::InvalidConfiguration(_))));
+
+	let kept = insert_outbox(&pool, 10, true).await;
+	let unwritable = std::env::temp_dir().join(format!("ruva_missing_{}", *SnowFlake::generate())).join("outbox.jsonl");
+	let res = OutBoxRetention::new(pool.clone(), retention).archive(OutBoxArchive::JsonLines(unwritable)).run().await;
+	assert!(matches!(res, Err(BaseError::ArchiveError(_))));
+	assert!(exists(&pool, "service_outbox", kept).await);
+	common::drop_database(pool, &name).await;
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(std::sync::Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[internally_notifiable]
+struct TicketIssued {
+	event_id: i64,
+	limit: i64,
+}
+
+struct CounterEventHandler;
+impl CounterEventHandler {
+	async fn increase_counter(self, event: TicketIssued, context: &mut Context) -> Result<(), TestError> {
+		sqlx::query("INSERT INTO test_ticket_counter (event_id, count) VALUES ($1, 1) ON CONFLICT (event_id) DO UPDATE SET count = test_ticket_counter.count + 1")
+			.bind(event.event_id)
+			.execute(context.transaction())
+			.await
+			.map_err(BaseError::from)?;
+		Ok(())
+	}
+
+	async fn check_limit(self, event: TicketIssued, context: &mut Context) -> Result<(), TestError> {
+		let count: i64 = sqlx::query_scalar("SELECT count FROM test_ticket_counter WHERE event_id = $1")
+			.bind(event.event_id)
+			.fetch_one(context.transaction())
+			.await
+			.map_err(BaseError::from)?;
+		if count > event.limit {
+			return Err(TestError::BaseError(BaseError::ServiceError));
+		}
+		Ok(())
+	}
+}
+
+init_event_handler!(
+	TestError,
+	|_| CounterEventHandler,
+	#[pre_commit]
+	TicketIssued: [increase_counter, check_limit]
+);
+
+#[derive(Debug)]
+struct IssueTicket {
+	id: i64,
+	event_id: i64,
+	limit: i64,
+}
+impl TCommand for IssueTicket {}
+
+async fn issue_ticket(cmd: IssueTicket, context: &mut Context) -> Result<(), TestError> {
+	sqlx::query("INSERT INTO test_ticket (id, event_id) VALUES ($1, $2)")
+		.bind(cmd.id)
+		.bind(cmd.event_id)
+		.execute(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+	context.set_current_events(
+		vec![TicketIssued {
+			event_id: cmd.event_id,
+			limit: cmd.limit,
+		}
+		.to_message()]
+		.into(),
+	);
+	Ok(())
+}
+
+register_uow_services!((), TestError, IssueTicket => issue_ticket);
+
+#[tokio::test]
+async fn test_failing_pre_commit_handler_rolls_back_command() {
+	let pool = common::connect().await;
+	common::execute_ddl(
+		&pool,
+		r#"
+		CREATE TABLE IF NOT EXISTS test_ticket (id BIGINT PRIMARY KEY, event_id BIGINT NOT NULL);
+		CREATE TABLE IF NOT EXISTS test_ticket_counter (event_id BIGINT PRIMARY KEY, count BIGINT NOT NULL);
+		"#,
+	)
+	.await;
+	let event_id = *SnowFlake::generate();
+
+	// WHEN limit allows only one ticket
+	for _ in 0..2 {
+		let _ = MessageBus
+			.execute_and_wait(
+				IssueTicket {
+					id: *SnowFlake::generate(),
+					event_id,
+					limit: 1,
+				},
+				std::sync::Arc::new(pool.clone()),
+			)
+			.await;
+	}
+
+	// THEN second command is rolled back along with counter update
+	let tickets: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM test_ticket WHERE event_id = $1")
+		.bind(event_id)
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	let count: i64 = sqlx::query_scalar("SELECT count FROM test_ticket_counter WHERE event_id = $1")
+		.bind(event_id)
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert_eq!(tickets, 1);
+	assert_eq!(count, 1);
+}
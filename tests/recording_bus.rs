@@ -0,0 +1,117 @@
+#![cfg(feature = "testing")]
+
+use ruva::testing::*;
+use ruva::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[internally_notifiable]
+struct OrderPlaced {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[internally_notifiable]
+struct StockReserved {
+	id: i64,
+}
+
+static NOTIFIED: AtomicUsize = AtomicUsize::new(0);
+static SHIPPED: AtomicUsize = AtomicUsize::new(0);
+
+struct OrderEventHandler;
+impl OrderEventHandler {
+	async fn reserve_stock(self, event: OrderPlaced, context: &mut Context) -> Result<(), TestError> {
+		context.set_current_events(vec![StockReserved { id: event.id }.to_message()].into());
+		Ok(())
+	}
+	async fn notify_customer(self, _event: OrderPlaced) -> Result<(), TestError> {
+		NOTIFIED.fetch_add(1, Ordering::SeqCst);
+		Ok(())
+	}
+	async fn ship(self, _event: StockReserved) -> Result<(), TestError> {
+		SHIPPED.fetch_add(1, Ordering::SeqCst);
+		Ok(())
+	}
+}
+
+init_event_handler!(
+	TestError,
+	|_| OrderEventHandler,
+	OrderPlaced: [#[transaction(none)] reserve_stock, notify_customer],
+	StockReserved: [ship],
+);
+
+#[derive(Debug)]
+struct PlaceOrder {
+	id: i64,
+}
+impl TCommand for PlaceOrder {}
+
+// * Command service that doesn't need database
+struct PlaceOrderService(AtomicContextManager, PlaceOrder);
+impl TCommandService<(), TestError> for PlaceOrderService {
+	async fn execute(self) -> Result<(), TestError> {
+		let mut context = Context::new(self.0);
+		context.set_current_events(vec![OrderPlaced { id: self.1.id }.to_message()].into());
+		context.send_internally_notifiable_messages().await;
+		Ok(())
+	}
+}
+
+impl TMessageBus<(), TestError, PlaceOrder> for MessageBus {
+	fn command_handler(&self, context_manager: AtomicContextManager, cmd: PlaceOrder) -> impl TCommandService<(), TestError> {
+		PlaceOrderService(context_manager, cmd)
+	}
+}
+
+#[tokio::test]
+async fn test_recording_bus_records_choreography_and_stubs_handlers() {
+	// * Every registered handler runs and is recorded in order
+	let bus = RecordingBus::<TestError, _>::new(MessageBus);
+	bus.execute_and_wait(PlaceOrder { id: 1 }, Arc::new(InMemoryConnection::default())).await.unwrap();
+
+	assert_eq!(bus.commands(), vec!["PlaceOrder { id: 1 }"]);
+	assert_eq!(bus.topics(), vec!["OrderPlaced", "StockReserved"]);
+	assert_eq!(bus.handlers(), vec![("OrderPlaced".to_string(), 0), ("OrderPlaced".to_string(), 1), ("StockReserved".to_string(), 0)]);
+	let records = bus
+		.records()
+		.into_iter()
+		.map(|record| match record {
+			Record::Command(_) => "command".to_string(),
+			Record::Event(event) => event.metadata().topic,
+			Record::Handler { topic, index } => format!("{}[{}]", topic, index),
+		})
+		.collect::<Vec<_>>();
+	assert_eq!(records, vec!["command", "OrderPlaced", "OrderPlaced[0]", "StockReserved", "OrderPlaced[1]", "StockReserved[0]"]);
+	assert_eq!((NOTIFIED.load(Ordering::SeqCst), SHIPPED.load(Ordering::SeqCst)), (1, 1));
+
+	// * Stubbed handler runs in place of the registered ones, and disabled ones don't run
+	let stubbed = Arc::new(AtomicUsize::new(0));
+	let bus = RecordingBus::<TestError, _>::new(MessageBus).disable("StockReserved").stub("OrderPlaced", {
+		let stubbed = stubbed.clone();
+		move |_, _| -> Future<TestError> {
+			stubbed.fetch_add(1, Ordering::SeqCst);
+			Box::pin(async { Ok(()) })
+		}
+	});
+	bus.publish(OrderPlaced { id: 2 }.to_message(), Arc::new(InMemoryConnection::default())).await.unwrap();
+	bus.publish(StockReserved { id: 2 }.to_message(), Arc::new(InMemoryConnection::default())).await.unwrap();
+
+	assert!(bus.commands().is_empty());
+	assert_eq!(bus.topics(), vec!["OrderPlaced", "StockReserved"]);
+	assert_eq!(bus.handlers(), vec![("OrderPlaced".to_string(), 0)]);
+	assert_eq!(stubbed.load(Ordering::SeqCst), 1);
+	assert_eq!((NOTIFIED.load(Ordering::SeqCst), SHIPPED.load(Ordering::SeqCst)), (1, 1));
+}
@@ -0,0 +1,179 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Serialize)]
+struct Member {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Member)]
+struct MemberJoined {
+	#[identifier]
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Member)]
+struct PointGiven {
+	#[identifier]
+	id: i64,
+}
+
+init_event_handler!(TestError, |_| (),);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PointFailure {
+	None,
+	Rollback,
+	// * Returns before either commit or rollback, leaving the nested unit of work open
+	EarlyReturn,
+}
+
+#[derive(Debug)]
+struct JoinMember {
+	id: i64,
+	point_failure: PointFailure,
+}
+impl TCommand for JoinMember {}
+
+fn point_limit_exceeded(failure: PointFailure) -> Result<(), TestError> {
+	match failure {
+		PointFailure::EarlyReturn => Err(TestError::BaseError(BaseError::ServiceError)),
+		_ => Ok(()),
+	}
+}
+
+// * Reusable transactional logic that is also run on its own
+async fn give_point(id: i64, failure: PointFailure, context: &mut Context) -> Result<(), TestError> {
+	context.begin().await?;
+	sqlx::query("INSERT INTO test_member_point (member_id) VALUES ($1)")
+		.bind(id)
+		.execute(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+	context.set_current_events(vec![PointGiven { id }.to_message()].into());
+	point_limit_exceeded(failure)?;
+	if failure == PointFailure::Rollback {
+		context.rollback().await?;
+		return Err(TestError::BaseError(BaseError::ServiceError));
+	}
+	context.commit().await?;
+	Ok(())
+}
+
+async fn join_member(cmd: JoinMember, context: &mut Context) -> Result<(), TestError> {
+	sqlx::query("INSERT INTO test_member (id) VALUES ($1)")
+		.bind(cmd.id)
+		.execute(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+	context.set_current_events(vec![MemberJoined { id: cmd.id }.to_message()].into());
+
+	// * Failure to give point must not fail joining
+	let _ = give_point(cmd.id, cmd.point_failure, context).await;
+	Ok(())
+}
+
+register_uow_services!((), TestError, JoinMember => join_member);
+
+async fn setup() -> sqlx::PgPool {
+	let pool = common::connect().await;
+	common::execute_ddl(
+		&pool,
+		r#"
+		CREATE TABLE IF NOT EXISTS test_member (id BIGINT PRIMARY KEY);
+		CREATE TABLE IF NOT EXISTS test_member_point (member_id BIGINT PRIMARY KEY);
+		"#,
+	)
+	.await;
+	pool
+}
+
+async fn count(pool: &sqlx::PgPool, query: &str, id: i64) -> i64 {
+	sqlx::query_scalar(query).bind(id).fetch_one(pool).await.unwrap()
+}
+
+async fn outbox_topics(pool: &sqlx::PgPool, id: i64) -> Vec<String> {
+	sqlx::query_scalar("SELECT topic FROM service_outbox WHERE aggregate_id = $1::TEXT ORDER BY topic")
+		.bind(id)
+		.fetch_all(pool)
+		.await
+		.unwrap()
+}
+
+#[tokio::test]
+async fn test_nested_unit_of_work_is_committed_with_outer_one() {
+	let pool = setup().await;
+	let id = *SnowFlake::generate();
+
+	MessageBus
+		.execute_and_wait(
+			JoinMember {
+				id,
+				point_failure: PointFailure::None,
+			},
+			Arc::new(pool.clone()),
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(count(&pool, "SELECT COUNT(*) FROM test_member WHERE id = $1", id).await, 1);
+	assert_eq!(count(&pool, "SELECT COUNT(*) FROM test_member_point WHERE member_id = $1", id).await, 1);
+	assert_eq!(outbox_topics(&pool, id).await, vec!["MemberJoined", "PointGiven"]);
+}
+
+#[tokio::test]
+async fn test_rollback_of_nested_unit_of_work_keeps_outer_one() {
+	let pool = setup().await;
+	let id = *SnowFlake::generate();
+
+	MessageBus
+		.execute_and_wait(
+			JoinMember {
+				id,
+				point_failure: PointFailure::Rollback,
+			},
+			Arc::new(pool.clone()),
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(count(&pool, "SELECT COUNT(*) FROM test_member WHERE id = $1", id).await, 1);
+	assert_eq!(count(&pool, "SELECT COUNT(*) FROM test_member_point WHERE member_id = $1", id).await, 0);
+	assert_eq!(outbox_topics(&pool, id).await, vec!["MemberJoined"]);
+}
+
+#[tokio::test]
+async fn test_nested_unit_of_work_left_open_is_rolled_back_and_outer_one_is_committed() {
+	let pool = setup().await;
+	let id = *SnowFlake::generate();
+
+	MessageBus
+		.execute_and_wait(
+			JoinMember {
+				id,
+				point_failure: PointFailure::EarlyReturn,
+			},
+			Arc::new(pool.clone()),
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(count(&pool, "SELECT COUNT(*) FROM test_member WHERE id = $1", id).await, 1);
+	assert_eq!(count(&pool, "SELECT COUNT(*) FROM test_member_point WHERE member_id = $1", id).await, 0);
+	assert_eq!(outbox_topics(&pool, id).await, vec!["MemberJoined"]);
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "testing")]
+
+use ruva::testing::*;
+use ruva::*;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[internally_notifiable]
+struct OrderPlaced {
+	id: i64,
+}
+
+// * Connection of in-memory state handlers work on
+#[derive(Default)]
+struct Warehouse(Mutex<Vec<String>>);
+impl TConnection for Warehouse {}
+
+struct WarehouseHandler(AtomicContextManager);
+impl WarehouseHandler {
+	fn log(&self, entry: String) {
+		self.0.conn.downcast_ref::<Warehouse>().unwrap().0.lock().unwrap().push(entry);
+	}
+	async fn reserve_stock(self, event: OrderPlaced) -> Result<(), TestError> {
+		self.log(format!("reserved {}", event.id));
+		Ok(())
+	}
+	// ! Assumes stock is reserved already, which holds only when handlers run in registration order
+	async fn confirm_order(self, event: OrderPlaced) -> Result<(), TestError> {
+		let reserved = self.0.conn.downcast_ref::<Warehouse>().unwrap().0.lock().unwrap().contains(&format!("reserved {}", event.id));
+		self.log(format!("confirmed {} {}", event.id, if reserved { "after reservation" } else { "before reservation" }));
+		Ok(())
+	}
+}
+
+init_event_handler!(
+	TestError,
+	WarehouseHandler,
+	#[async]
+	OrderPlaced: [reserve_stock, confirm_order],
+);
+
+#[derive(Debug)]
+struct PlaceOrder {
+	id: i64,
+}
+impl TCommand for PlaceOrder {}
+
+struct PlaceOrderService(AtomicContextManager, PlaceOrder);
+impl TCommandService<(), TestError> for PlaceOrderService {
+	async fn execute(self) -> Result<(), TestError> {
+		let mut context = Context::new(self.0);
+		context.set_current_events(vec![OrderPlaced { id: self.1.id }.to_message()].into());
+		context.send_internally_notifiable_messages().await;
+		Ok(())
+	}
+}
+
+impl TMessageBus<(), TestError, PlaceOrder> for MessageBus {
+	fn command_handler(&self, context_manager: AtomicContextManager, cmd: PlaceOrder) -> impl TCommandService<(), TestError> {
+		PlaceOrderService(context_manager, cmd)
+	}
+}
+
+async fn simulate(bus: SimulatedBus<TestError, MessageBus>) -> (Vec<Step>, Vec<String>) {
+	let warehouse = Arc::new(Warehouse::default());
+	bus.execute_and_wait(PlaceOrder { id: 1 }, warehouse.clone()).await.unwrap();
+	let log = warehouse.0.lock().unwrap().clone();
+	(bus.trace(), log)
+}
+
+#[tokio::test]
+async fn test_same_seed_replays_same_interleaving() {
+	let mut logs = vec![];
+	for seed in 0..32 {
+		let (trace, log) = simulate(SimulatedBus::new(MessageBus, seed)).await;
+		assert_eq!(simulate(SimulatedBus::new(MessageBus, seed)).await, (trace.clone(), log.clone()));
+		assert!(trace.contains(&Step::Finished {
+			topic: "OrderPlaced".into(),
+			index: 1
+		}));
+		logs.push(log);
+	}
+
+	// * Different seeds run async handlers in different order
+	assert!(logs.iter().any(|log| log[1] == "confirmed 1 after reservation"));
+	assert!(logs.iter().any(|log| log[0] == "confirmed 1 before reservation"));
+}
+
+#[tokio::test]
+async fn test_injected_failures_do_not_stop_processing() {
+	let (trace, log) = simulate(SimulatedBus::new(MessageBus, 7).failure_rate(1.0)).await;
+	assert!(log.is_empty());
+	assert_eq!(trace.iter().filter(|step| matches!(step, Step::Failed { .. })).count(), 2);
+
+	let (trace, log) = simulate(SimulatedBus::new(MessageBus, 7).failure_rate(0.0).max_delay(0).yield_rate(0.0)).await;
+	assert_eq!(log, vec!["reserved 1", "confirmed 1 after reservation"]);
+	assert!(!trace.iter().any(|step| matches!(step, Step::Failed { .. } | Step::Delayed { .. } | Step::Yielded { .. })));
+}
+
+#[tokio::test]
+#[should_panic(expected = "Replay with RUVA_SIMULATION_SEED=")]
+async fn test_fuzz_reports_seed_that_breaks_ordering_assumption() {
+	fuzz(0..64, async |seed| {
+		let (_, log) = simulate(SimulatedBus::new(MessageBus, seed)).await;
+		assert!(!log.iter().any(|entry| entry.ends_with("before reservation")), "{:?}", log);
+	})
+	.await;
+}
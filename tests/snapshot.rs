@@ -0,0 +1,164 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Clone, Serialize, Deserialize)]
+struct Account {
+	id: i64,
+	balance: i64,
+	// * Not kept in snapshot, counting events applied since loaded from it
+	#[adapter_ignore]
+	applied: i64,
+}
+
+static SCHEMA_VERSION: AtomicI32 = AtomicI32::new(1);
+
+impl TEventSourced for Account {
+	fn apply(&mut self, outbox: &OutBox) -> Result<(), BaseError> {
+		let event: Deposited = outbox.decode()?;
+		self.id = event.id;
+		self.balance += event.amount;
+		self.applied += 1;
+		Ok(())
+	}
+
+	fn snapshot_frequency() -> i64 {
+		3
+	}
+
+	fn schema_version() -> i32 {
+		SCHEMA_VERSION.load(Ordering::SeqCst)
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TEvent)]
+#[externally_notifiable(Account)]
+struct Deposited {
+	#[identifier]
+	id: i64,
+	amount: i64,
+}
+
+#[derive(Debug)]
+struct Deposit {
+	id: i64,
+	amount: i64,
+}
+impl TCommand for Deposit {}
+
+async fn deposit(cmd: Deposit, context: &mut Context) -> Result<(), TestError> {
+	context.set_current_events(vec![Deposited { id: cmd.id, amount: cmd.amount }.to_message()].into());
+	Ok(())
+}
+
+init_event_handler!(TestError, |_| (),);
+register_uow_services!((), TestError, Deposit => deposit);
+
+async fn deposit_times(pool: &sqlx::PgPool, id: i64, times: usize) {
+	for _ in 0..times {
+		MessageBus.execute_and_wait(Deposit { id, amount: 10 }, Arc::new(pool.clone())).await.unwrap();
+	}
+}
+
+#[tokio::test]
+async fn test_aggregate_is_loaded_from_snapshot_and_later_events() {
+	let pool = common::connect().await;
+	let store = SnapshotStore::new(pool.clone());
+	let id = *SnowFlake::generate();
+
+	let loaded = store.load::<Account>(id).await.unwrap();
+	assert_eq!((loaded.aggregate.balance, loaded.version), (0, 0));
+
+	// * Snapshot is taken once enough events are applied
+	deposit_times(&pool, id, 2).await;
+	assert_eq!(store.load::<Account>(id).await.unwrap().version, 2);
+	assert!(store.latest::<Account>(&id.to_string()).await.unwrap().is_none());
+
+	deposit_times(&pool, id, 2).await;
+	let loaded = store.load::<Account>(id).await.unwrap();
+	assert_eq!((loaded.aggregate.balance, loaded.version), (40, 4));
+	// * Aggregate is returned as replayed even when snapshot is taken
+	assert_eq!(loaded.aggregate.applied, 4);
+	let snapshot = store.latest::<Account>(&id.to_string()).await.unwrap().unwrap();
+	assert_eq!((snapshot.aggregate.balance, snapshot.version), (40, 4));
+
+	// * Only events after the snapshot are applied on top of it
+	sqlx::query("UPDATE service_aggregate_snapshot SET state = jsonb_set(state, '{balance}', '1000') WHERE aggregate_name = 'Account' AND aggregate_id = $1")
+		.bind(id.to_string())
+		.execute(&pool)
+		.await
+		.unwrap();
+	deposit_times(&pool, id, 1).await;
+	let loaded = store.load::<Account>(id).await.unwrap();
+	assert_eq!((loaded.aggregate.id, loaded.aggregate.balance, loaded.version), (id, 1010, 5));
+
+	// * Snapshot of other schema version is ignored and replaced
+	SCHEMA_VERSION.store(2, Ordering::SeqCst);
+	assert!(store.latest::<Account>(&id.to_string()).await.unwrap().is_none());
+	let loaded = store.load::<Account>(id).await.unwrap();
+	assert_eq!((loaded.aggregate.balance, loaded.version), (50, 5));
+	let snapshot = store.latest::<Account>(&id.to_string()).await.unwrap().unwrap();
+	assert_eq!((snapshot.aggregate.balance, snapshot.version), (50, 5));
+	assert_eq!(store.invalidate::<Account>().await.unwrap(), 0);
+
+	// * Older schema version doesn't replace snapshot of newer one
+	SCHEMA_VERSION.store(1, Ordering::SeqCst);
+	deposit_times(&pool, id, 3).await;
+	assert_eq!(store.load::<Account>(id).await.unwrap().version, 8);
+	let stored: (i32, i64) = sqlx::query_as("SELECT schema_version, version FROM service_aggregate_snapshot WHERE aggregate_name = 'Account' AND aggregate_id = $1")
+		.bind(id.to_string())
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert_eq!(stored, (2, 5));
+}
+
+#[tokio::test]
+async fn test_aggregate_is_replayed_after_outbox_retention() {
+	// * Retention applies to the whole table, so it runs on database of its own
+	let name = common::unique_database_name("ruva_test_snapshot_retention");
+	let pool = common::connect_to_database(&name).await;
+	let store = SnapshotStore::new(pool.clone());
+	let id = *SnowFlake::generate();
+
+	// * Fewer events than snapshot frequency, so the aggregate is rebuilt only from outboxes
+	deposit_times(&pool, id, 2).await;
+	sqlx::query(
+		r#"
+		INSERT INTO service_outbox (id, aggregate_id, aggregate_name, topic, state, processed)
+		VALUES ($1, $1::TEXT, 'Retention', 'Retained', '{}', TRUE)
+		"#,
+	)
+	.bind(*SnowFlake::generate())
+	.execute(&pool)
+	.await
+	.unwrap();
+	sqlx::query("UPDATE service_outbox SET processed = TRUE, create_dt = NOW() - INTERVAL '10 days'")
+		.execute(&pool)
+		.await
+		.unwrap();
+
+	let report = OutBoxRetention::new(pool.clone(), std::time::Duration::from_secs(7 * 24 * 60 * 60))
+		.keep_event_sourced::<Account>()
+		.run()
+		.await
+		.unwrap();
+	assert_eq!(report.deleted, 1);
+
+	let loaded = store.load::<Account>(id).await.unwrap();
+	assert_eq!((loaded.aggregate.id, loaded.aggregate.balance, loaded.version), (id, 20, 2));
+	common::drop_database(pool, &name).await;
+}
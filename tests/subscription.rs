@@ -0,0 +1,140 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Serialize)]
+struct Ticket {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Ticket)]
+struct TicketIssued {
+	#[identifier]
+	id: i64,
+}
+
+#[derive(Debug)]
+struct IssueTicket {
+	id: i64,
+}
+impl TCommand for IssueTicket {}
+
+async fn issue_ticket(cmd: IssueTicket, context: &mut Context) -> Result<(), TestError> {
+	context.set_current_events(vec![TicketIssued { id: cmd.id }.to_message()].into());
+	Ok(())
+}
+
+init_event_handler!(TestError, |_| (),);
+register_uow_services!((), TestError, IssueTicket => issue_ticket);
+
+/// Records ids of tickets, ignoring events written by other tests.
+#[derive(Clone, Default)]
+struct RecordingSubscriber(Arc<Mutex<Vec<String>>>);
+impl TEventSubscriber for RecordingSubscriber {
+	async fn handle(&self, outbox: &OutBox) -> Result<(), BaseError> {
+		if outbox.aggregate_name == "Ticket" {
+			self.0.lock().unwrap().push(outbox.aggregate_id.clone());
+		}
+		Ok(())
+	}
+}
+
+impl RecordingSubscriber {
+	fn handled(&self) -> Vec<String> {
+		self.0.lock().unwrap().clone()
+	}
+
+	async fn wait_for_count(&self, count: usize, timeout: Duration) -> bool {
+		tokio::time::timeout(timeout, async {
+			while self.0.lock().unwrap().len() < count {
+				tokio::time::sleep(Duration::from_millis(10)).await;
+			}
+		})
+		.await
+		.is_ok()
+	}
+}
+
+async fn issue_tickets(pool: &sqlx::PgPool, channel: &str, count: usize) -> Vec<String> {
+	let mut ids = vec![];
+	for _ in 0..count {
+		let id = *SnowFlake::generate();
+		MessageBus
+			.execute_and_wait_with_context(IssueTicket { id }, ContextManager::new(Arc::new(pool.clone())).with_outbox_channel(channel))
+			.await
+			.unwrap();
+		ids.push(id.to_string());
+	}
+	ids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subscription_switches_from_catch_up_to_live_without_gap_or_duplicate() {
+	let pool = common::connect().await;
+	let channel = "ruva_test_subscription";
+	let name = format!("test-subscription-{}", *SnowFlake::generate());
+	let start = StreamPosition::end(&pool).await.unwrap();
+
+	// * History written before the subscription starts
+	let history = issue_tickets(&pool, channel, 5).await;
+
+	// * Transaction that began before catch-up and commits only after live events
+	let late = (*SnowFlake::generate()).to_string();
+	let mut late_trx = pool.begin().await.unwrap();
+	sqlx::query("INSERT INTO service_outbox (id, aggregate_id, aggregate_name, topic, state) VALUES ($1, $2, 'Ticket', 'TicketIssued', '{}')")
+		.bind(*SnowFlake::generate())
+		.bind(&late)
+		.execute(&mut *late_trx)
+		.await
+		.unwrap();
+
+	let subscriber = RecordingSubscriber::default();
+	let subscription = tokio::spawn({
+		let (pool, name, subscriber) = (pool.clone(), name.clone(), subscriber.clone());
+		async move {
+			EventSubscription::new(pool, name)
+				.listen(channel)
+				.start_from(start)
+				.batch_size(2)
+				.poll_interval(Duration::from_millis(100))
+				.run(&subscriber)
+				.await
+		}
+	});
+
+	// * Events of the open transaction and after it are held back until it commits
+	let live = issue_tickets(&pool, channel, 5).await;
+	assert!(subscriber.wait_for_count(5, Duration::from_secs(10)).await);
+	tokio::time::sleep(Duration::from_millis(300)).await;
+	assert_eq!(subscriber.handled(), history);
+
+	sqlx::query("SELECT pg_notify($1, '')").bind(channel).execute(&mut *late_trx).await.unwrap();
+	late_trx.commit().await.unwrap();
+	assert!(subscriber.wait_for_count(11, Duration::from_secs(10)).await);
+	tokio::time::sleep(Duration::from_millis(300)).await;
+	subscription.abort();
+
+	let expected = history.iter().cloned().chain([late]).chain(live).collect::<Vec<_>>();
+	assert_eq!(subscriber.handled(), expected);
+
+	// * Resumed from checkpoint, nothing is handled again
+	let subscription = EventSubscription::new(pool.clone(), name);
+	assert!(subscription.checkpoint().await.unwrap().unwrap() > start);
+	let resumed = RecordingSubscriber::default();
+	subscription.catch_up(&resumed).await.unwrap();
+	assert!(resumed.handled().is_empty());
+}
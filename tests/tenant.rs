@@ -0,0 +1,107 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+#[aggregate(Debug, Serialize)]
+struct Account {
+	id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[externally_notifiable(Account)]
+struct AccountOpened {
+	#[identifier]
+	id: i64,
+}
+
+#[derive(Debug)]
+struct OpenAccount {
+	id: i64,
+}
+impl TCommand for OpenAccount {}
+
+async fn open_account(cmd: OpenAccount, context: &mut Context) -> Result<(), TestError> {
+	sqlx::query("INSERT INTO test_account (id) VALUES ($1)")
+		.bind(cmd.id)
+		.execute(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+	context.set_current_events(vec![AccountOpened { id: cmd.id }.to_message()].into());
+	Ok(())
+}
+
+init_event_handler!(TestError, |_| (),);
+
+register_uow_services!((), TestError, OpenAccount => open_account);
+
+/// Database of its own for the tenant, dropped with [common::drop_database] at the end of test.
+async fn tenant_database(tenant_id: &str) -> (sqlx::PgPool, String) {
+	let name = common::unique_database_name(&format!("ruva_test_{tenant_id}"));
+	let pool = common::connect_to_database(&name).await;
+	common::execute_ddl(&pool, "CREATE TABLE IF NOT EXISTS test_account (id BIGINT PRIMARY KEY);").await;
+	(pool, name)
+}
+
+async fn count(pool: &sqlx::PgPool, query: &str, id: i64) -> i64 {
+	sqlx::query_scalar(query).bind(id).fetch_one(pool).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_command_is_routed_to_database_of_its_tenant() {
+	let (tenant_a, name_a) = tenant_database("tenant_a").await;
+	let (tenant_b, name_b) = tenant_database("tenant_b").await;
+	let connections: Arc<dyn TConnection> = Arc::new(
+		TenantConnections::default()
+			.with_tenant("tenant_a", Arc::new(tenant_a.clone()))
+			.with_tenant("tenant_b", Arc::new(tenant_b.clone())),
+	);
+	let id = *SnowFlake::generate();
+
+	MessageBus
+		.execute_and_wait_with_context(OpenAccount { id }, ContextManager::new(Arc::clone(&connections)).with_tenant_id("tenant_a"))
+		.await
+		.unwrap();
+
+	assert_eq!(count(&tenant_a, "SELECT COUNT(*) FROM test_account WHERE id = $1", id).await, 1);
+	assert_eq!(count(&tenant_a, "SELECT COUNT(*) FROM service_outbox WHERE aggregate_id = $1::TEXT", id).await, 1);
+	assert_eq!(count(&tenant_b, "SELECT COUNT(*) FROM test_account WHERE id = $1", id).await, 0);
+	assert_eq!(count(&tenant_b, "SELECT COUNT(*) FROM service_outbox WHERE aggregate_id = $1::TEXT", id).await, 0);
+
+	drop(connections);
+	common::drop_database(tenant_a, &name_a).await;
+	common::drop_database(tenant_b, &name_b).await;
+}
+
+#[tokio::test]
+async fn test_command_of_unknown_or_missing_tenant_fails() {
+	let (tenant_a, name_a) = tenant_database("tenant_a").await;
+	let connections: Arc<dyn TConnection> = Arc::new(TenantConnections::default().with_tenant("tenant_a", Arc::new(tenant_a.clone())));
+
+	let res = MessageBus
+		.execute_and_wait_with_context(
+			OpenAccount { id: *SnowFlake::generate() },
+			ContextManager::new(Arc::clone(&connections)).with_tenant_id("tenant_unknown"),
+		)
+		.await;
+	assert!(matches!(res, Err(TestError::BaseError(BaseError::UnknownTenant(tenant_id))) if tenant_id == "tenant_unknown"));
+
+	let res = MessageBus
+		.execute_and_wait_with_context(OpenAccount { id: *SnowFlake::generate() }, ContextManager::new(Arc::clone(&connections)))
+		.await;
+	assert!(matches!(res, Err(TestError::BaseError(BaseError::TenantNotGiven))));
+
+	drop(connections);
+	common::drop_database(tenant_a, &name_a).await;
+}
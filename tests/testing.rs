@@ -0,0 +1,137 @@
+#![cfg(feature = "testing")]
+
+use ruva::testing::*;
+use ruva::*;
+
+#[derive(Debug, PartialEq)]
+enum AccountError {
+	InsufficientBalance,
+	Base(String),
+}
+
+impl From<BaseError> for AccountError {
+	fn from(value: BaseError) -> Self {
+		Self::Base(format!("{:?}", value))
+	}
+}
+
+#[aggregate(Debug, Serialize, Deserialize)]
+struct Account {
+	id: i64,
+	balance: i64,
+}
+
+impl Account {
+	fn withdraw(&mut self, amount: i64) -> Result<(), AccountError> {
+		if self.balance < amount {
+			return Err(AccountError::InsufficientBalance);
+		}
+		self.balance -= amount;
+		self.raise_event(Withdrawn { id: self.id, amount }.to_message());
+		Ok(())
+	}
+}
+
+impl TEventSourced for Account {
+	fn apply(&mut self, outbox: &OutBox) -> Result<(), BaseError> {
+		match outbox.topic.as_str() {
+			"Deposited" => {
+				let event: Deposited = outbox.decode()?;
+				self.id = event.id;
+				self.balance += event.amount;
+			}
+			"Withdrawn" => self.balance -= outbox.decode::<Withdrawn>()?.amount,
+			_ => (),
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TEvent)]
+#[externally_notifiable(Account)]
+struct Deposited {
+	#[identifier]
+	id: i64,
+	amount: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TEvent)]
+#[externally_notifiable(Account)]
+struct Withdrawn {
+	#[identifier]
+	id: i64,
+	amount: i64,
+}
+
+#[derive(Debug)]
+struct Withdraw {
+	id: i64,
+	amount: i64,
+}
+
+async fn withdraw(cmd: Withdraw, context: &mut Context) -> Result<i64, AccountError> {
+	let mut account: Account = InMemoryConnection::of(context).unwrap().load(cmd.id)?;
+	account.withdraw(cmd.amount)?;
+	context.event_hook(&mut account);
+	Ok(account.balance)
+}
+
+#[tokio::test]
+async fn test_command_handler_scenario() {
+	let then = Scenario::new()
+		.given([Deposited { id: 1, amount: 100 }.to_message(), Withdrawn { id: 1, amount: 30 }.to_message()])
+		.when(Withdraw { id: 1, amount: 50 }, withdraw)
+		.await
+		.then_ok()
+		.then_response(20)
+		.then_events([Withdrawn { id: 1, amount: 50 }])
+		.then_outboxes([("Withdrawn", serde_json::json!({ "id": 1, "amount": 50 }))]);
+	assert_eq!(then.outboxes[0].aggregate_name, "Account");
+
+	Scenario::new()
+		.given([Deposited { id: 1, amount: 10 }.to_message()])
+		.when(Withdraw { id: 1, amount: 50 }, withdraw)
+		.await
+		.then_error(AccountError::InsufficientBalance)
+		.then_no_events();
+}
+
+#[test]
+fn test_aggregate_scenario() {
+	AggregateScenario::<Account>::given([Deposited { id: 1, amount: 100 }.to_message()])
+		.when(|account| account.withdraw(40))
+		.then_ok()
+		.then_events([Withdrawn { id: 1, amount: 40 }])
+		.then_state(|account| assert_eq!(account.balance, 60));
+
+	AggregateScenario::given_state(Account { id: 2, ..Default::default() })
+		.when(|account| account.withdraw(1))
+		.then_error(AccountError::InsufficientBalance)
+		.then_no_events();
+}
+
+#[test]
+#[should_panic(expected = "assertion failed")]
+fn test_unexpected_event_fails_scenario() {
+	AggregateScenario::<Account>::given([Deposited { id: 1, amount: 100 }.to_message()])
+		.when(|account| account.withdraw(40))
+		.then_events([Withdrawn { id: 1, amount: 41 }]);
+}
+
+#[tokio::test]
+async fn test_scenario_with_fixed_clock_and_sequential_ids() {
+	let now = SystemClock.now();
+	let then = Scenario::new()
+		.with_clock(FixedClock::new(now))
+		.with_id_generator(SequentialIdGenerator::new(1))
+		.given([Deposited { id: 1, amount: 100 }.to_message()])
+		.when(Withdraw { id: 1, amount: 50 }, async |cmd: Withdraw, context: &mut Context| {
+			assert_eq!(context.now(), now);
+			assert_eq!(context.next_id(), Id::Int(1));
+			withdraw(cmd, context).await
+		})
+		.await
+		.then_ok();
+	assert_eq!(then.outboxes[0].id, Id::Int(2));
+	assert_eq!(then.outboxes[0].create_dt, now);
+}
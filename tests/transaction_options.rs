@@ -0,0 +1,167 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+use std::sync::{
+	atomic::{AtomicUsize, Ordering},
+	Arc,
+};
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+}
+
+init_event_handler!(TestError, |_| (),);
+
+#[derive(Debug)]
+struct RecordIsolationLevel {
+	id: i64,
+}
+impl TCommand for RecordIsolationLevel {}
+
+async fn record_isolation_level(cmd: RecordIsolationLevel, context: &mut Context) -> Result<(), TestError> {
+	sqlx::query("INSERT INTO test_balance (id, amount, note) VALUES ($1, 0, current_setting('transaction_isolation'))")
+		.bind(cmd.id)
+		.execute(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+	Ok(())
+}
+
+#[derive(Debug)]
+struct OpenBalance {
+	id: i64,
+}
+impl TCommand for OpenBalance {}
+
+async fn open_balance(cmd: OpenBalance, context: &mut Context) -> Result<(), TestError> {
+	sqlx::query("INSERT INTO test_balance (id, amount, note) VALUES ($1, 0, current_setting('transaction_isolation'))")
+		.bind(cmd.id)
+		.execute(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+	Ok(())
+}
+
+static DEPOSIT_ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+static WITHDRAWAL_ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone)]
+struct Deposit {
+	id: i64,
+}
+impl TCommand for Deposit {}
+
+#[derive(Debug, Clone)]
+struct Withdraw {
+	id: i64,
+}
+impl TCommand for Withdraw {}
+
+// * On the first attempt, the row is updated by other transaction in between read and write, which fails on serialization
+async fn add_amount(id: i64, amount: i64, attempts: &AtomicUsize, context: &mut Context) -> Result<(), TestError> {
+	let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+	let _: i64 = sqlx::query_scalar("SELECT amount FROM test_balance WHERE id = $1")
+		.bind(id)
+		.fetch_one(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+
+	if attempt == 0 {
+		let conn = context.context_manager().connection()?;
+		let pool = conn.downcast_ref::<sqlx::PgPool>().unwrap();
+		sqlx::query("UPDATE test_balance SET amount = amount + 100 WHERE id = $1").bind(id).execute(pool).await.unwrap();
+	}
+
+	sqlx::query("UPDATE test_balance SET amount = amount + $2 WHERE id = $1")
+		.bind(id)
+		.bind(amount)
+		.execute(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+	Ok(())
+}
+
+async fn deposit(cmd: Deposit, context: &mut Context) -> Result<(), TestError> {
+	add_amount(cmd.id, 10, &DEPOSIT_ATTEMPTS, context).await
+}
+
+async fn withdraw(cmd: Withdraw, context: &mut Context) -> Result<(), TestError> {
+	add_amount(cmd.id, -10, &WITHDRAWAL_ATTEMPTS, context).await
+}
+
+register_uow_services!(
+	(),
+	TestError,
+	#[transaction(isolation = Serializable, read_only)]
+	RecordIsolationLevel => record_isolation_level,
+	#[transaction(isolation = RepeatableRead)]
+	OpenBalance => open_balance,
+	#[transaction(isolation = Serializable)]
+	#[retry(3, backoff = std::time::Duration::from_millis(1))]
+	Deposit => deposit,
+	#[transaction(isolation = Serializable)]
+	Withdraw => withdraw
+);
+
+async fn setup() -> sqlx::PgPool {
+	let pool = common::connect().await;
+	common::execute_ddl(&pool, "CREATE TABLE IF NOT EXISTS test_balance (id BIGINT PRIMARY KEY, amount BIGINT NOT NULL, note TEXT);").await;
+	pool
+}
+
+async fn amount(pool: &sqlx::PgPool, id: i64) -> i64 {
+	sqlx::query_scalar("SELECT amount FROM test_balance WHERE id = $1").bind(id).fetch_one(pool).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_read_only_transaction_rejects_write() {
+	let pool = setup().await;
+
+	let res = MessageBus.execute_and_wait(RecordIsolationLevel { id: *SnowFlake::generate() }, Arc::new(pool)).await;
+
+	assert!(matches!(res, Err(TestError::DatabaseError(err)) if err.contains("read-only")));
+}
+
+#[tokio::test]
+async fn test_isolation_level_is_set_per_command() {
+	let pool = setup().await;
+	let id = *SnowFlake::generate();
+
+	MessageBus.execute_and_wait(OpenBalance { id }, Arc::new(pool.clone())).await.unwrap();
+
+	// * Isolation level is recorded by the statement run in the transaction of the command
+	let level: String = sqlx::query_scalar("SELECT note FROM test_balance WHERE id = $1").bind(id).fetch_one(&pool).await.unwrap();
+	assert_eq!(level, "repeatable read");
+}
+
+#[tokio::test]
+async fn test_command_is_retried_on_serialization_failure() {
+	let pool = setup().await;
+	let id = *SnowFlake::generate();
+	MessageBus.execute_and_wait(OpenBalance { id }, Arc::new(pool.clone())).await.unwrap();
+
+	MessageBus.execute_and_wait(Deposit { id }, Arc::new(pool.clone())).await.unwrap();
+
+	assert_eq!(DEPOSIT_ATTEMPTS.load(Ordering::SeqCst), 2);
+	assert_eq!(amount(&pool, id).await, 110);
+}
+
+#[tokio::test]
+async fn test_serialization_failure_is_returned_without_retry() {
+	let pool = setup().await;
+	let id = *SnowFlake::generate();
+	MessageBus.execute_and_wait(OpenBalance { id }, Arc::new(pool.clone())).await.unwrap();
+
+	let res = MessageBus.execute_and_wait(Withdraw { id }, Arc::new(pool.clone())).await;
+
+	assert!(matches!(res, Err(TestError::BaseError(BaseError::TransactionConflict(_)))));
+	assert_eq!(WITHDRAWAL_ATTEMPTS.load(Ordering::SeqCst), 1);
+	assert_eq!(amount(&pool, id).await, 100);
+}
@@ -0,0 +1,126 @@
+#![cfg(feature = "sqlx-postgres")]
+mod common;
+
+use ruva::*;
+
+#[derive(Debug, Clone, ApplicationError)]
+#[crates(ruva)]
+#[allow(dead_code)]
+enum TestError {
+	StopSentinel,
+	StopSentinelWithEvent(std::sync::Arc<dyn TEvent>),
+	DatabaseError(String),
+	BaseError(BaseError),
+	CounterUnavailable,
+}
+
+#[derive(Debug, Clone, Serialize, TEvent)]
+#[internally_notifiable]
+struct OrderPlaced {
+	id: i64,
+	fail_to_count: bool,
+}
+
+struct OrderEventHandler;
+impl OrderEventHandler {
+	async fn count_order(self, event: OrderPlaced, context: &mut Context) -> Result<(), TestError> {
+		sqlx::query("INSERT INTO test_order_counter (order_id) VALUES ($1)")
+			.bind(event.id)
+			.execute(context.transaction())
+			.await
+			.map_err(BaseError::from)?;
+		if event.fail_to_count {
+			return Err(TestError::CounterUnavailable);
+		}
+		Ok(())
+	}
+
+	async fn issue_invoice(self, event: OrderPlaced, context: &mut Context) -> Result<(), TestError> {
+		sqlx::query("INSERT INTO test_order_invoice (order_id) VALUES ($1)")
+			.bind(event.id)
+			.execute(context.transaction())
+			.await
+			.map_err(BaseError::from)?;
+		Ok(())
+	}
+}
+
+init_event_handler!(
+	TestError,
+	|_| OrderEventHandler,
+	OrderPlaced: [
+		#[transaction(join)] count_order,
+		#[transaction(own)] issue_invoice
+	]
+);
+
+#[derive(Debug)]
+struct PlaceOrder {
+	id: i64,
+	fail_to_count: bool,
+}
+impl TCommand for PlaceOrder {}
+
+async fn place_order(cmd: PlaceOrder, context: &mut Context) -> Result<(), TestError> {
+	sqlx::query("INSERT INTO test_order (id) VALUES ($1)")
+		.bind(cmd.id)
+		.execute(context.transaction())
+		.await
+		.map_err(BaseError::from)?;
+	context.set_current_events(
+		vec![OrderPlaced {
+			id: cmd.id,
+			fail_to_count: cmd.fail_to_count,
+		}
+		.to_message()]
+		.into(),
+	);
+	Ok(())
+}
+
+register_uow_services!((), TestError, PlaceOrder => place_order);
+
+async fn setup() -> sqlx::PgPool {
+	let pool = common::connect().await;
+	common::execute_ddl(
+		&pool,
+		r#"
+		CREATE TABLE IF NOT EXISTS test_order (id BIGINT PRIMARY KEY);
+		CREATE TABLE IF NOT EXISTS test_order_counter (order_id BIGINT PRIMARY KEY);
+		CREATE TABLE IF NOT EXISTS test_order_invoice (order_id BIGINT PRIMARY KEY);
+		"#,
+	)
+	.await;
+	pool
+}
+
+async fn count(pool: &sqlx::PgPool, table: &str, id: i64) -> i64 {
+	let column = if table == "test_order" { "id" } else { "order_id" };
+	sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table} WHERE {column} = $1")).bind(id).fetch_one(pool).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_joining_and_own_transaction_handlers_are_committed() {
+	let pool = setup().await;
+	let id = *SnowFlake::generate();
+
+	MessageBus.execute_and_wait(PlaceOrder { id, fail_to_count: false }, std::sync::Arc::new(pool.clone())).await.unwrap();
+
+	assert_eq!(count(&pool, "test_order", id).await, 1);
+	assert_eq!(count(&pool, "test_order_counter", id).await, 1);
+	assert_eq!(count(&pool, "test_order_invoice", id).await, 1);
+}
+
+#[tokio::test]
+async fn test_failure_of_joining_handler_rolls_back_command() {
+	let pool = setup().await;
+	let id = *SnowFlake::generate();
+
+	let res = MessageBus.execute_and_wait(PlaceOrder { id, fail_to_count: true }, std::sync::Arc::new(pool.clone())).await;
+
+	// * Command returns the error of the joined handler as it is
+	assert!(matches!(res, Err(TestError::CounterUnavailable)));
+	assert_eq!(count(&pool, "test_order", id).await, 0);
+	assert_eq!(count(&pool, "test_order_counter", id).await, 0);
+	assert_eq!(count(&pool, "test_order_invoice", id).await, 0);
+}
@@ -0,0 +1,175 @@
+#![cfg(feature = "webhook")]
+mod common;
+
+use hmac::{Hmac, Mac};
+use ruva::*;
+use sha2::Sha256;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[derive(Debug, Clone)]
+struct Request {
+	headers: Vec<(String, String)>,
+	body: Vec<u8>,
+}
+
+impl Request {
+	fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+	}
+}
+
+/// Local HTTP server responding with the scripted statuses in order, repeating the last one.
+#[derive(Clone)]
+struct Receiver {
+	url: String,
+	requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Receiver {
+	async fn start(statuses: Vec<u16>) -> Self {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}/events", listener.local_addr().unwrap());
+		let requests = Arc::new(Mutex::new(vec![]));
+
+		tokio::spawn({
+			let requests = requests.clone();
+			async move {
+				loop {
+					let (mut stream, _) = listener.accept().await.unwrap();
+					let request = read_request(&mut stream).await;
+					let status = {
+						let mut requests = requests.lock().unwrap();
+						requests.push(request);
+						*statuses.get(requests.len() - 1).or(statuses.last()).unwrap()
+					};
+					let response = format!("HTTP/1.1 {} Scripted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+					stream.write_all(response.as_bytes()).await.unwrap();
+					stream.shutdown().await.unwrap();
+				}
+			}
+		});
+		Self { url, requests }
+	}
+
+	fn requests(&self) -> Vec<Request> {
+		self.requests.lock().unwrap().clone()
+	}
+}
+
+async fn read_request(stream: &mut tokio::net::TcpStream) -> Request {
+	let mut buf = vec![];
+	let header_end = loop {
+		let mut chunk = [0u8; 1024];
+		let n = stream.read(&mut chunk).await.unwrap();
+		buf.extend_from_slice(&chunk[..n]);
+		if let Some(pos) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+			break pos;
+		}
+	};
+
+	let headers = String::from_utf8_lossy(&buf[..header_end])
+		.lines()
+		.skip(1)
+		.filter_map(|line| line.split_once(':'))
+		.map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+		.collect::<Vec<_>>();
+	let request = Request { headers, body: vec![] };
+	let length: usize = request.header("content-length").map(|length| length.parse().unwrap()).unwrap_or(0);
+
+	let mut body = buf[header_end + 4..].to_vec();
+	while body.len() < length {
+		let mut chunk = [0u8; 1024];
+		let n = stream.read(&mut chunk).await.unwrap();
+		body.extend_from_slice(&chunk[..n]);
+	}
+	Request { body, ..request }
+}
+
+fn endpoint(receiver: &Receiver, secret: &str) -> WebhookEndpoint {
+	WebhookEndpoint::new(&receiver.url, secret).retry_policy(RetryPolicy::new(2).backoff(Duration::from_millis(10)))
+}
+
+async fn delivery(pool: &sqlx::PgPool, outbox_id: Id, endpoint: &str) -> (String, i32, Option<i32>) {
+	sqlx::query_as("SELECT status, attempts, response_status FROM service_outbox_delivery WHERE outbox_id = $1 AND endpoint = $2")
+		.bind(outbox_id)
+		.bind(endpoint)
+		.fetch_one(pool)
+		.await
+		.unwrap()
+}
+
+#[tokio::test]
+async fn test_webhook_delivers_signed_outbox_and_records_status() {
+	let pool = common::connect().await;
+	common::execute_ddl(
+		&pool,
+		r#"
+		CREATE TABLE IF NOT EXISTS service_outbox_delivery (
+			outbox_id BIGINT NOT NULL,
+			endpoint TEXT NOT NULL,
+			status TEXT NOT NULL,
+			attempts INT NOT NULL,
+			response_status INT,
+			last_error TEXT,
+			update_dt TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+			PRIMARY KEY (outbox_id, endpoint)
+		);
+		"#,
+	)
+	.await;
+
+	// * Recovers from transient failure within retries
+	let flaky = Receiver::start(vec![500, 200]).await;
+	// * Never recovers
+	let broken = Receiver::start(vec![500]).await;
+	// * Not subscribing to the topic
+	let other = Receiver::start(vec![200]).await;
+
+	let now = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+	let publisher = WebhookPublisher::new(pool.clone())
+		.with_clock(FixedClock::new(now))
+		.endpoint("WebhookOrderPlaced", endpoint(&flaky, "flaky-secret"))
+		.endpoint("WebhookOrderPlaced", endpoint(&broken, "broken-secret"))
+		.endpoint("WebhookOrderCancelled", endpoint(&other, "other-secret"));
+
+	let outbox = OutBox::new("1".into(), "Order".into(), "WebhookOrderPlaced".into(), r#"{"id":1}"#.into(), &SystemClock, &SnowFlakeIdGenerator);
+	assert!(matches!(
+		publisher.publish(&outbox).await,
+		Err(BaseError::WebhookDeliveryFailed { endpoint, status: Some(500) }) if endpoint == broken.url
+	));
+
+	let requests = flaky.requests();
+	assert_eq!(requests.len(), 2);
+	let request = requests.last().unwrap();
+	assert_eq!(request.header("content-type"), Some("application/cloudevents+json"));
+	assert_eq!(request.header(DELIVERY_HEADER), Some(outbox.id.to_string().as_str()));
+
+	// * Signature covers timestamp and delivery id so that captured request can't be replayed later or as other delivery
+	let timestamp: i64 = request.header(TIMESTAMP_HEADER).unwrap().parse().unwrap();
+	assert_eq!(timestamp, now.timestamp());
+	let mut mac = Hmac::<Sha256>::new_from_slice(b"flaky-secret").unwrap();
+	mac.update(format!("{}.{}.", timestamp, outbox.id).as_bytes());
+	mac.update(&request.body);
+	let expected = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+	assert_eq!(request.header(SIGNATURE_HEADER), Some(format!("sha256={}", expected).as_str()));
+
+	let event: CloudEvent = serde_json::from_slice(&request.body).unwrap();
+	assert_eq!(event.id, outbox.id.to_string());
+	assert_eq!(event.ty, "WebhookOrderPlaced");
+	assert_eq!(event.data, Some(serde_json::json!({ "id": 1 })));
+
+	assert_eq!(delivery(&pool, outbox.id, &flaky.url).await, ("delivered".to_string(), 2, Some(200)));
+	// * First attempt and two retries
+	assert_eq!(broken.requests().len(), 3);
+	assert_eq!(delivery(&pool, outbox.id, &broken.url).await, ("failed".to_string(), 3, Some(500)));
+	assert!(other.requests().is_empty());
+
+	// * Publishing again only retries the endpoint that failed
+	assert!(publisher.publish(&outbox).await.is_err());
+	assert_eq!(flaky.requests().len(), 2);
+	assert_eq!(broken.requests().len(), 6);
+	assert_eq!(delivery(&pool, outbox.id, &broken.url).await, ("failed".to_string(), 6, Some(500)));
+}